@@ -1,9 +1,11 @@
 // wengwengweng
 
 use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
 
 use super::utils;
+use super::*;
 
 pub struct Browser {
 
@@ -35,10 +37,6 @@ pub enum ItemType {
 	Music,
 }
 
-pub enum Error {
-	IO,
-}
-
 pub enum Selection {
 	Item(usize),
 	Back,
@@ -81,7 +79,7 @@ impl FilterList {
 
 impl Browser {
 
-	pub fn new(path: PathBuf) -> Self {
+	pub fn new(path: PathBuf) -> Result<Self, Error> {
 
 		let mut browser = Browser {
 			listings: Vec::new(),
@@ -91,38 +89,44 @@ impl Browser {
 			markings: Vec::new(),
 		};
 
-		browser.cd(path);
+		browser.cd(path)?;
 
-		return browser;
+		return Ok(browser);
 
 	}
 
-	pub fn from_file(path: PathBuf) -> Result<Self, ()> {
+	pub fn from_file(path: PathBuf) -> Result<Self, Error> {
 
-		if path.is_file() {
+		if path.is_dir() {
+			return Err(Error::is_directory(&path));
+		}
 
-			if let Some(parent) = path.parent() {
+		if !path.is_file() {
+			return Err(Error::not_found(&path));
+		}
 
-				let mut browser = Self::new(parent.to_path_buf());
+		let parent = match path.parent() {
+			Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+			_ => PathBuf::from("."),
+		};
 
-				browser.select_item(&path);
+		let mut browser = Self::new(parent)?;
 
-				return Ok(browser);
+		browser.select_item(&path);
 
-			} else {
-				return Err(());
-			}
-
-		} else {
-			return Err(());
-		}
+		return Ok(browser);
 
 	}
 
-	pub fn cd(&mut self, path: PathBuf) {
+	/// go into a directory, stays where it was if it can't be listed
+	pub fn cd(&mut self, path: PathBuf) -> Result<(), Error> {
 
-		self.path = path;
-		self.refresh();
+		let old_path = std::mem::replace(&mut self.path, path);
+
+		if let Err(e) = self.refresh() {
+			self.path = old_path;
+			return Err(e);
+		}
 
 		if self.listings.get(0).is_some() {
 			self.selection = Selection::Item(0);
@@ -130,6 +134,8 @@ impl Browser {
 			self.selection = Selection::Back;
 		}
 
+		return Ok(());
+
 	}
 
 	pub fn select_item(&mut self, path: &PathBuf) {
@@ -148,16 +154,18 @@ impl Browser {
 
 	}
 
-	pub fn back(&mut self) {
+	pub fn back(&mut self) -> Result<(), Error> {
 
 		let old_path = self.path.clone();
 
 		if let Some(parent) = self.path.parent() {
-			self.cd(parent.to_path_buf());
+			self.cd(parent.to_path_buf())?;
 		}
 
 		self.select_item(&old_path);
 
+		return Ok(());
+
 	}
 
 	pub fn selected(&self) -> Option<&Item> {
@@ -202,71 +210,80 @@ impl Browser {
 
 	}
 
-	pub fn refresh(&mut self) {
+	pub fn refresh(&mut self) -> Result<(), Error> {
+
+		if self.path.is_file() {
+			return Err(Error::NotDirectory(self.path.clone()));
+		}
+
+		let paths = self.path
+			.read_dir()
+			.map_err(|e| Error::from_io(&self.path, FileOp::List, e))?;
 
 		self.listings = vec![];
 
 		let mut dirs = vec![];
 		let mut files = vec![];
 
-		if let Ok(paths) = self.path.read_dir() {
-
-			for p in paths
-				.filter_map(Result::ok)
-				.map(|e| e.path()) {
-
-				if let Some(name) = utils::get_fname(&p) {
+		for p in paths
+			.filter_map(Result::ok)
+			.map(|e| e.path()) {
 
-					if !self.conf.ignores.check(name) {
+			if let Some(name) = utils::get_fname(&p) {
 
-						if p.is_dir() {
+				if !self.conf.ignores.check(name) {
 
-							dirs.push(Item {
-								name: name.to_owned(),
-								path: p,
-								kind: ItemType::Folder,
-							});
+					if p.is_dir() {
 
-						} else if p.is_file() {
+						dirs.push(Item {
+							name: name.to_owned(),
+							path: p,
+							kind: ItemType::Folder,
+						});
 
-							let mut kind = ItemType::Text;
+					} else if p.is_file() {
 
-							if let Some(ext) = p.extension() {
+						let mut kind = ItemType::Text;
 
-								if ext == "png" {
-									kind = ItemType::Image;
-								}
+						if let Some(ext) = p.extension() {
 
+							if ext == "png" {
+								kind = ItemType::Image;
 							}
 
-							files.push(Item {
-								name: name.to_owned(),
-								path: p,
-								kind: kind,
-							});
-
 						}
 
+						files.push(Item {
+							name: name.to_owned(),
+							path: p,
+							kind: kind,
+						});
+
 					}
-				}
 
+				}
 			}
 
-// 			dirs.sort();
-// 			files.sort();
+		}
+
+// 		dirs.sort();
+// 		files.sort();
 
-			self.listings.append(&mut dirs);
-			self.listings.append(&mut files);
+		self.listings.append(&mut dirs);
+		self.listings.append(&mut files);
 
-		}
+		return Ok(());
 
 	}
 
-	pub fn mkdir(&mut self, name: &str) {
+	pub fn mkdir(&mut self, name: &str) -> Result<(), Error> {
 
-		if fs::create_dir(name).is_ok() {
-			self.select_item(&PathBuf::from(name));
-		}
+		fs::create_dir(name)
+			.map_err(|e| Error::from_io(Path::new(name), FileOp::Write, e))?;
+
+		self.select_item(&PathBuf::from(name));
+
+		return Ok(());
 
 	}
 
@@ -4,4 +4,6 @@ mod browser;
 mod utils;
 
 pub use browser::*;
+pub use buffer::Error;
+pub use buffer::FileOp;
 
@@ -0,0 +1,200 @@
+// wengwengweng
+
+use super::*;
+
+/// which way to look for a bracket
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Dir {
+	Forward,
+	Backward,
+}
+
+impl Buffer {
+
+	/// get the byte ranges of strings and comments in a line, brackets in them don't count
+	fn skipped_spans(&self, line: &str) -> Vec<(usize, usize)> {
+
+		let mut spans = vec![];
+		let mut start = 0;
+
+		for t in self.filetype.syntax.parse(line) {
+
+			let end = start + t.text.len();
+
+			match t.span {
+				Span::String | Span::Comment => spans.push((start, end)),
+				_ => {},
+			}
+
+			start = end;
+
+		}
+
+		return spans;
+
+	}
+
+	/// if a position is inside a string or a comment
+	pub fn in_string_or_comment(&self, pos: Pos) -> bool {
+
+		let line = match self.get_line_at(pos.line) {
+			Some(line) => line,
+			None => return false,
+		};
+
+		let b = col_to_byte(&line, pos.col);
+
+		return self.skipped_spans(&line).iter().any(|(start, end)| b >= *start && b < *end);
+
+	}
+
+	/// get the open and close char of a bracket pair from the filetype, quotes are not brackets
+	pub fn bracket_of(&self, ch: char) -> Option<(char, char)> {
+		return self.pair_of(ch).filter(|(open, close)| open != close);
+	}
+
+	/// look for the unmatched bracket of a pair starting at a byte of a line
+	fn scan_bracket(&self, ln: Line, byte: usize, open: char, close: char, dir: Dir, skip: bool) -> Option<Pos> {
+
+		let (want, other) = match dir {
+			Dir::Forward => (close, open),
+			Dir::Backward => (open, close),
+		};
+
+		let last = self.line_count() as Line;
+		let mut depth = 0;
+		let mut ln = ln;
+		let mut first = true;
+
+		while ln >= 1 && ln <= last {
+
+			let line = self.get_line_at(ln)?;
+			let spans = if skip {
+				self.skipped_spans(&line)
+			} else {
+				vec![]
+			};
+
+			let (lo, hi) = match (first, dir) {
+				(true, Dir::Forward) => (byte, line.len()),
+				(true, Dir::Backward) => (0, byte),
+				(false, _) => (0, line.len()),
+			};
+
+			let mut chars: Vec<(usize, char)> = line[lo..hi]
+				.char_indices()
+				.map(|(i, c)| (i + lo, c))
+				.filter(|(i, _)| !spans.iter().any(|(start, end)| i >= start && i < end))
+				.collect();
+
+			if dir == Dir::Backward {
+				chars.reverse();
+			}
+
+			for (i, c) in chars {
+				if c == other {
+					depth += 1;
+				} else if c == want {
+					if depth == 0 {
+						return Some(Pos::new(ln, byte_to_col(&line, i)));
+					}
+					depth -= 1;
+				}
+			}
+
+			first = false;
+
+			match dir {
+				Dir::Forward => ln += 1,
+				Dir::Backward => ln -= 1,
+			}
+
+		}
+
+		return None;
+
+	}
+
+	/// find the unmatched open bracket before a position, or at it
+	pub fn find_open_at(&self, pos: Pos, open: char, close: char) -> Option<Pos> {
+
+		let line = self.get_line_at(pos.line)?;
+		let b = col_to_byte(&line, pos.col);
+
+		if line[b..].starts_with(open) {
+			return Some(pos);
+		}
+
+		return self.scan_bracket(pos.line, b, open, close, Dir::Backward, !self.in_string_or_comment(pos));
+
+	}
+
+	/// find the unmatched close bracket after a position
+	pub fn find_close_at(&self, pos: Pos, open: char, close: char) -> Option<Pos> {
+
+		let line = self.get_line_at(pos.line)?;
+		let b = col_to_byte(&line, pos.col + 1);
+
+		return self.scan_bracket(pos.line, b, open, close, Dir::Forward, !self.in_string_or_comment(pos));
+
+	}
+
+	/// get a bracket at a position and the one matching it, as (open, close)
+	pub fn matching_pair_at(&self, pos: Pos) -> Option<(Pos, Pos)> {
+
+		let line = self.get_line_at(pos.line)?;
+		let b = col_to_byte(&line, pos.col);
+		let ch = line[b..].chars().next()?;
+		let (open, close) = self.bracket_of(ch)?;
+
+		if ch == open {
+			return Some((pos, self.find_close_at(pos, open, close)?));
+		}
+
+		let skip = !self.in_string_or_comment(pos);
+
+		return Some((self.scan_bracket(pos.line, b, open, close, Dir::Backward, skip)?, pos));
+
+	}
+
+	/// get the bracket under the cursor and the one matching it, for highlighting
+	pub fn matching_pair(&self) -> Option<(Pos, Pos)> {
+		return self.matching_pair_at(self.cursor);
+	}
+
+	/// find where % goes from a position, the match of the first bracket at or after it on the line
+	pub fn find_match_at(&self, pos: Pos) -> Option<Pos> {
+
+		let line = self.get_line_at(pos.line)?;
+		let from = col_to_byte(&line, pos.col);
+		let spans = if self.in_string_or_comment(pos) {
+			vec![]
+		} else {
+			self.skipped_spans(&line)
+		};
+
+		for (i, c) in line[from..].char_indices() {
+
+			let i = i + from;
+
+			if self.bracket_of(c).is_none() || spans.iter().any(|(start, end)| i >= *start && i < *end) {
+				continue;
+			}
+
+			let at = Pos::new(pos.line, byte_to_col(&line, i));
+			let (open, close) = self.matching_pair_at(at)?;
+
+			return Some(if open == at {
+				close
+			} else {
+				open
+			});
+
+		}
+
+		return None;
+
+	}
+
+}
+
@@ -1,12 +1,21 @@
 // wengwengweng
 
 use std::fs;
+use std::cmp;
+use std::mem;
+use std::rc::Rc;
+use std::cell::RefCell;
 use std::path::PathBuf;
+use std::time::Instant;
+use std::time::SystemTime;
 use std::collections::HashSet;
+use std::collections::HashMap;
 
 use regex::Regex;
-use clipboard::ClipboardProvider;
-use clipboard::ClipboardContext;
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+use serde::Serialize;
+use serde::Deserialize;
 
 use super::*;
 
@@ -16,26 +25,51 @@ pub struct Buffer {
 	pub cursor: Pos,
 	pub child_cursors: Vec<Pos>,
 	pub path: PathBuf,
-	pub content: Vec<String>,
+	pub content: Rope,
 	pub rendered: Vec<Vec<SpannedText>>,
-	pub undo_stack: Vec<State>,
-	pub redo_stack: Vec<State>,
-	pub clipboard: ClipboardContext,
+	pub history: UndoTree,
+	pub registers: Registers,
 	pub modified: bool,
 	pub conf: Conf,
 	pub log: Vec<String>,
-	pub filetype: FileType,
+	pub filetype: Rc<FileType>,
+	pub filetypes: Rc<FTRegistry>,
+	pub search_opts: SearchOpts,
+	pub fold_method: FoldMethod,
+	pub layout: Layout,
+	pub format: FileFormat,
+	/// copy the file to name~ before saving over it
+	pub backup: bool,
+	/// keep unsaved changes in a swap file
+	pub swap: bool,
 	invalid_chars: HashSet<char>,
+	pub(crate) saved_hash: u64,
+	tracked: Vec<usize>,
+	undo_depth: usize,
+	pub(crate) search_history: Vec<String>,
+	pub(crate) last_search: Option<Query>,
+	pub(crate) pending_keys: Vec<Key>,
+	pub(crate) register: Option<char>,
+	pub(crate) recording: Option<Recording>,
+	pub(crate) last_macro: Option<char>,
+	pub(crate) macro_depth: usize,
+	pub(crate) marks: HashMap<char, usize>,
+	pub(crate) jumps: Vec<usize>,
+	pub(crate) jump_index: usize,
+	pub(crate) folds: Vec<usize>,
+	pub(crate) swap_time: Option<Instant>,
+	pub(crate) swap_hash: u64,
+	pub(crate) recovery: Option<SwapFile>,
+	pub(crate) watcher: Option<Watcher>,
+	pub(crate) disk_mtime: Option<SystemTime>,
+	pub(crate) disk_base: String,
+	pub(crate) disk_change: Option<DiskChange>,
+	pub(crate) revision: u64,
+	pub(crate) match_cache: RefCell<Option<MatchCache>>,
+	pub(crate) fold_cache: RefCell<FoldCache>,
 
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct State {
-	content: Vec<String>,
-	cursor: Pos,
-	modified: bool,
-}
-
 #[derive(Clone)]
 pub struct Conf {
 	break_chars: HashSet<char>,
@@ -46,6 +80,12 @@ pub enum Event {
 		from: Pos,
 		to: Pos
 	},
+	/// the file changed on disk and was read again
+	Reloaded,
+	/// the file changed on disk while there are unsaved edits
+	Conflict,
+	/// the file was removed from disk
+	Deleted,
 }
 
 impl Default for Conf {
@@ -86,7 +126,7 @@ pub type Line = u32;
 pub type Col = u32;
 pub type IndentLevel = u32;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Pos {
 	pub line: Line,
 	pub col: Col,
@@ -107,10 +147,17 @@ impl Pos {
 pub enum Mode {
 	Normal,
 	Insert,
-	Command,
-	Select(Vec<Range>),
+	Command {
+		text: String,
+	},
+	Select {
+		kind: SelectKind,
+		anchor: Pos,
+	},
 	Search {
 		text: String,
+		origin: Pos,
+		history: Option<usize>,
 	},
 }
 
@@ -137,16 +184,14 @@ impl SpannedText {
 
 }
 
-pub enum Error {
-	IO,
-}
-
 impl Buffer {
 
 	pub fn from_file(path: PathBuf) -> Result<Self, Error> {
+		return Self::from_file_with(path, FTRegistry::shared());
+	}
 
-		let mut registry = FTRegistry::new();
-		let fname = format!("{}", path.display());
+	/// open a file with the filetypes picked from a registry
+	pub fn from_file_with(path: PathBuf, filetypes: Rc<FTRegistry>) -> Result<Self, Error> {
 
 		let mut invalid_chars = HashSet::new();
 
@@ -160,27 +205,63 @@ impl Buffer {
 
 			mode: Mode::Normal,
 			path: path,
-			content: Vec::new(),
+			content: Rope::new(),
 			rendered: Vec::with_capacity(1024),
 			cursor: Pos::new(1, 1),
 			child_cursors: Vec::new(),
 			conf: Conf::default(),
-			undo_stack: Vec::new(),
-			redo_stack: Vec::new(),
+			history: UndoTree::new(),
 			modified: false,
-			clipboard: ClipboardProvider::new().unwrap(),
+			registers: Registers::new(default_clipboard()),
 			log: Vec::new(),
-			filetype: ft_test::rust(),
+			filetype: Rc::new(FileType::default()),
+			filetypes: filetypes,
+			search_opts: SearchOpts::default(),
+			fold_method: FoldMethod::Indent,
+			layout: Layout::default(),
+			format: FileFormat::default(),
+			backup: false,
+			swap: true,
 			invalid_chars: invalid_chars,
+			saved_hash: 0,
+			tracked: Vec::new(),
+			undo_depth: 0,
+			search_history: Vec::new(),
+			last_search: None,
+			pending_keys: Vec::new(),
+			register: None,
+			recording: None,
+			last_macro: None,
+			macro_depth: 0,
+			marks: HashMap::new(),
+			jumps: Vec::new(),
+			jump_index: 0,
+			folds: Vec::new(),
+			swap_time: None,
+			swap_hash: 0,
+			recovery: None,
+			watcher: None,
+			disk_mtime: None,
+			disk_base: String::new(),
+			disk_change: None,
+			revision: 0,
+			match_cache: RefCell::new(None),
+			fold_cache: RefCell::new(FoldCache::default()),
 
 		};
 
-		if buf.read().is_ok() {
-			return Ok(buf);
-		} else {
-			return Err(Error::IO);
+		buf.read()?;
+		buf.detect_filetype();
+
+		if let Some(history) = UndoTree::load(&buf.path, buf.saved_hash) {
+			buf.history = history;
 		}
 
+		buf.restore_macros();
+		buf.check_swap();
+
+		return Ok(buf);
+
 	}
 
 	pub fn log(&mut self, info: &str) {
@@ -189,13 +270,29 @@ impl Buffer {
 
 	pub fn render(&mut self, start: usize, end: usize) {
 
-		let start = clamp(start, 1, self.content.len());
-		let end = clamp(end, 1, self.content.len());
+		let start = clamp(start, 1, self.line_count());
+		let end = clamp(end, 1, self.line_count());
+
+		let closed = self.closed();
+		let folds = self.closed_folds();
+
+		self.rendered = (start..=end)
+			.filter(|ln| !closed.hides(*ln as Line))
+			.filter_map(|ln| {
+
+				let text = self.get_line_at(ln as Line)?;
+				let mut line = self.filetype.syntax.parse(&text);
+
+				// a closed fold shows how many lines it hides
+				if let Some((s, e)) = folds.iter().find(|(s, _)| *s == ln as Line) {
+					line.push(SpannedText {
+						span: Span::Comment,
+						text: format!(" ... {} lines", e - s),
+					});
+				}
+
+				return Some(line);
 
-		self.rendered = self.content[start - 1..end]
-			.iter()
-			.map(|text| {
-				return self.filetype.syntax.parse(text);
 			})
 			.collect();
 
@@ -203,75 +300,133 @@ impl Buffer {
 
 	pub fn read(&mut self) -> Result<(), Error> {
 
-		if let Ok(content) = fs::read_to_string(&self.path) {
-
-			self.content = content
-				.lines()
-				.map(|st| String::from(st))
-				.collect();
+		if self.path.is_dir() {
+			return Err(Error::is_directory(&self.path));
+		}
 
-			return Ok(());
+		let bytes = fs::read(&self.path)
+			.map_err(|e| Error::from_io(&self.path, FileOp::Read, e))?;
 
-		} else {
+		let (content, format) = decode(&bytes)
+			.ok_or_else(|| Error::InvalidEncoding(self.path.clone(), None))?;
 
-			return Err(Error::IO);
+		self.content = Rope::from_str(&content);
+		self.revision += 1;
+		self.format = format;
+		self.saved_hash = utils::hash(self.content.chunks());
+		self.sync_disk();
 
-		}
+		return Ok(());
 
 	}
 
-	pub fn write(&self) {
+	/// save the content to the file, a read-only file is only overwritten with force
+	pub fn write(&mut self, force: bool) -> Result<(), Error> {
 
-		if let Ok(_) = fs::write(&self.path, &self.content.join("\n")) {
-			// ...
-		} else {
-			// ...
+		let bytes = encode(&self.content.to_string(), &self.format)
+			.map_err(|ch| Error::InvalidEncoding(self.path.clone(), Some(ch)))?;
+
+		// write through symlinks instead of replacing them
+		let path = fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+
+		if !force && is_read_only(&path) {
+			return Err(Error::ReadOnly(self.path.clone()));
+		}
+
+		if self.backup && path.exists() {
+			let backup = backup_file_path(&path);
+			fs::copy(&path, &backup)
+				.map_err(|e| Error::from_io(&backup, FileOp::Write, e))?;
 		}
 
+		write_atomic(&path, &bytes)
+			.map_err(|e| Error::from_io(&self.path, FileOp::Write, e))?;
+
+		self.push_undo();
+		self.history.mark_saved();
+		self.format.empty = self.format.empty && self.content.len_chars() == 0;
+		self.saved_hash = utils::hash(self.content.chunks());
+		self.modified = false;
+		self.save_history();
+		self.delete_swap();
+		self.sync_disk();
+
+		return Ok(());
+
 	}
 
-	/// get current state for undo/redo
-	pub fn get_state(&self) -> State {
-		return State {
-			content: self.content.clone(),
-			cursor: self.cursor.clone(),
-			modified: self.modified,
+	/// save the content to another file and keep editing that one, the path only changes if writing worked
+	pub fn write_as(&mut self, path: PathBuf, force: bool) -> Result<(), Error> {
+
+		let same = match (fs::canonicalize(&path), fs::canonicalize(&self.path)) {
+			(Ok(a), Ok(b)) => a == b,
+			_ => path == self.path,
 		};
-	}
 
-	/// set current state
-	pub fn set_state(&mut self, state: State) {
+		if !force && !same && path.exists() {
+			return Err(Error::Exists(path));
+		}
+
+		let old = mem::replace(&mut self.path, path);
 
-		self.content = state.content;
-		self.modified = state.modified;
-		self.move_to(state.cursor);
+		if let Err(e) = self.write(force) {
+			self.path = old;
+			return Err(e);
+		}
+
+		self.detect_filetype();
+
+		return Ok(());
 
 	}
 
-	/// push current state to undo stack
-	pub fn push_undo(&mut self) {
+	/// store the undo history in the user data dir, so it survives restarts
+	pub fn save_history(&mut self) {
 
-		let state = self.get_state();
+		self.push_undo();
 
-		if self.undo_stack.last() == Some(&state) {
-			return;
+		if let Err(e) = self.history.save(&self.path, self.saved_hash) {
+			self.log(&format!("failed to save undo history: {}", e));
 		}
 
-		self.undo_stack.push(state);
+	}
+
+	/// close the pending edits as one undo step
+	pub fn push_undo(&mut self) {
+		if self.undo_depth == 0 {
+			self.history.commit(self.cursor);
+		}
+	}
+
+	/// keep the edits until end_undo_group() in the current undo step
+	pub fn begin_undo_group(&mut self) {
+		self.undo_depth += 1;
+	}
 
+	/// stop grouping edits
+	pub fn end_undo_group(&mut self) {
+		self.undo_depth = self.undo_depth.saturating_sub(1);
 	}
 
-	/// push current state to redo stack
-	pub fn push_redo(&mut self) {
-		self.redo_stack.push(self.get_state());
+	/// apply edits from the undo history
+	fn apply_change(&mut self, change: Change) {
+
+		for edit in &change.edits {
+			self.apply_edit(edit);
+		}
+
+		self.modified = !self.history.is_saved();
+		self.move_to(change.cursor);
+
 	}
 
 	/// undo
 	pub fn undo(&mut self) {
 
-		if let Some(state) = self.undo_stack.pop() {
-			self.push_redo();
-			self.set_state(state);
+		self.push_undo();
+
+		if let Some(change) = self.history.undo() {
+			self.apply_change(change);
 		}
 
 	}
@@ -279,9 +434,70 @@ impl Buffer {
 	/// redo
 	pub fn redo(&mut self) {
 
-		if let Some(state) = self.redo_stack.pop() {
-			self.push_undo();
-			self.set_state(state);
+		self.push_undo();
+
+		if let Some(change) = self.history.redo() {
+			self.apply_change(change);
+		}
+
+	}
+
+	/// go back n undo states in time, across branches
+	pub fn earlier(&mut self, n: usize) {
+
+		self.push_undo();
+
+		if let Some(change) = self.history.earlier(n) {
+			self.apply_change(change);
+		}
+
+	}
+
+	/// go forward n undo states in time, across branches
+	pub fn later(&mut self, n: usize) {
+
+		self.push_undo();
+
+		if let Some(change) = self.history.later(n) {
+			self.apply_change(change);
+		}
+
+	}
+
+	/// go to the next sibling undo branch
+	pub fn next_branch(&mut self) {
+
+		self.push_undo();
+
+		if let Some(change) = self.history.switch_branch(true) {
+			self.apply_change(change);
+		}
+
+	}
+
+	/// go to the previous sibling undo branch
+	pub fn prev_branch(&mut self) {
+
+		self.push_undo();
+
+		if let Some(change) = self.history.switch_branch(false) {
+			self.apply_change(change);
+		}
+
+	}
+
+	/// describe where in the undo history we are, like "undo state 3 of 7"
+	pub fn undo_state(&self) -> String {
+		return format!("undo state {} of {}", self.history.current(), self.history.len() - 1);
+	}
+
+	/// go to an undo state by id
+	pub fn goto_state(&mut self, id: usize) {
+
+		self.push_undo();
+
+		if let Some(change) = self.history.goto(id) {
+			self.apply_change(change);
 		}
 
 	}
@@ -313,11 +529,16 @@ impl Buffer {
 	/// start command mode
 	pub fn start_command(&mut self) {
 
-		if let Mode::Command = self.mode {
-			return;
-		}
+		let text = match self.mode {
+			Mode::Command { .. } => return,
+			Mode::Select { .. } => String::from("'<,'>"),
+			_ => String::new(),
+		};
 
-		self.mode = Mode::Command;
+		self.end_select();
+		self.mode = Mode::Command {
+			text: text,
+		};
 
 	}
 
@@ -330,6 +551,8 @@ impl Buffer {
 
 		self.mode = Mode::Search {
 			text: String::new(),
+			origin: self.cursor,
+			history: None,
 		};
 
 	}
@@ -339,28 +562,162 @@ impl Buffer {
 		return self.modified;
 	}
 
+	/// get the number of lines
+	pub fn line_count(&self) -> usize {
+		return self.content.len_lines();
+	}
+
+	/// get the char index where a line starts
+	pub fn line_to_char(&self, ln: Line) -> Option<usize> {
+
+		if ln < 1 || ln as usize > self.line_count() {
+			return None;
+		}
+
+		return Some(self.content.line_to_char(ln as usize - 1));
+
+	}
+
+	/// get the char index of a position
+	pub fn pos_to_char(&self, pos: Pos) -> Option<usize> {
+
+		let start = self.line_to_char(pos.line)?;
+		let line = self.get_line_at(pos.line)?;
+		let byte = col_to_byte(&line, pos.col);
+
+		return Some(start + line[..byte].chars().count());
+
+	}
+
+	/// get the position of a char index
+	pub fn char_to_pos(&self, idx: usize) -> Pos {
+
+		let idx = clamp(idx, 0, self.content.len_chars());
+		let ln = self.content.char_to_line(idx);
+		let start = self.content.line_to_char(ln);
+		let byte = self.content.slice(start..idx).len_bytes();
+		let line = self.get_line_at(ln as Line + 1).unwrap_or_default();
+
+		return Pos::new(ln as Line + 1, byte_to_col(&line, byte));
+
+	}
+
+	/// get the char indices of a range, the end is inclusive and takes the line break if it's past the line end
+	pub fn range_to_chars(&self, r: Range) -> Option<(usize, usize)> {
+
+		// an end before the start on the same line is an empty range, like a whole empty line ending at col 0
+		if r.start.line == r.end.line && r.end.col < r.start.col {
+			let idx = self.pos_to_char(r.start)?;
+			return Some((idx, idx));
+		}
+
+		let (start, end) = if r.start <= r.end {
+			(r.start, r.end)
+		} else {
+			(r.end, r.start)
+		};
+
+		let start_idx = self.pos_to_char(start)?;
+		let mut end_idx = self.pos_to_char(Pos {
+			col: end.col + 1,
+			.. end
+		})?;
+
+		if end.col > self.line_len(end.line) && (end.line as usize) < self.line_count() {
+			end_idx = self.content.line_to_char(end.line as usize);
+		}
+
+		return Some((start_idx, cmp::max(start_idx, end_idx)));
+
+	}
+
+	/// replace the chars in a char range with text, recording it for undo
+	pub(crate) fn edit_chars(&mut self, start: usize, end: usize, text: &str) {
+
+		if start == end && text.is_empty() {
+			return;
+		}
+
+		let deleted = String::from(self.content.slice(start..end));
+		let edit = Edit::new(start, &deleted, text);
+
+		self.history.record(edit.clone(), self.cursor);
+		self.modified = true;
+		self.apply_edit(&edit);
+
+	}
+
+	/// apply an edit to the content
+	fn apply_edit(&mut self, edit: &Edit) {
+
+		let end = edit.at + edit.deleted_len();
+
+		if end > edit.at {
+			self.content.remove(edit.at..end);
+		}
+
+		if !edit.inserted.is_empty() {
+			self.content.insert(edit.at, &edit.inserted);
+		}
+
+		self.revision += 1;
+
+		for idx in &mut self.tracked {
+			*idx = shift_char(*idx, edit);
+		}
+
+		for idx in self.marks.values_mut().chain(self.jumps.iter_mut()).chain(self.folds.iter_mut()) {
+			*idx = shift_char(*idx, edit);
+		}
+
+	}
+
 	/// get content of a line
-	pub fn get_line_at(&self, ln: Line) -> Option<&String> {
-		return self.content.get(ln as usize - 1);
+	pub fn get_line_at(&self, ln: Line) -> Option<String> {
+
+		if ln < 1 || ln as usize > self.line_count() {
+			return None;
+		}
+
+		let mut line = String::from(self.content.line(ln as usize - 1));
+
+		if line.ends_with('\n') {
+			line.pop();
+		}
+
+		return Some(line);
+
 	}
 
 	/// get content of current line
-	pub fn get_line(&self) -> Option<&String> {
+	pub fn get_line(&self) -> Option<String> {
 		return self.get_line_at(self.cursor.line);
 	}
 
+	/// get the number of columns in a line
+	pub fn line_len(&self, ln: Line) -> Col {
+		return self.get_line_at(ln).map(|l| col_count(&l)).unwrap_or(0) as Col;
+	}
+
 	/// set content of a line
 	pub fn set_line_at(&mut self, ln: Line, content: &str) {
 
-		if self.content.get(ln as usize - 1).is_some() {
+		if let Some(line) = self.get_line_at(ln) {
 
-			if !self.modified {
-				self.push_undo();
-				self.redo_stack.clear();
-				self.modified = true;
+			if line == content {
+				return;
 			}
 
-			self.content.get_mut(ln as usize - 1).map(|s| *s = String::from(content));
+			// only touch the part that changed, to keep undo edits small
+			let old: Vec<char> = line.chars().collect();
+			let new: Vec<char> = content.chars().collect();
+			let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+			let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+			let text: String = new[prefix..new.len() - suffix].iter().collect();
+
+			if let Some(start) = self.line_to_char(ln) {
+				self.edit_chars(start + prefix, start + old.len() - suffix, &text);
+			}
 
 		}
 
@@ -374,30 +731,45 @@ impl Buffer {
 	/// delete secified line
 	pub fn del_line_at(&mut self, ln: Line) -> Line {
 
-		if ln as usize <= self.content.len() {
+		if ln >= 1 && ln as usize <= self.line_count() {
 
 			self.push_undo();
 
-			if !self.modified {
-				self.redo_stack.clear();
-				self.modified = true;
-			}
+			let lines = self.line_count();
+			let start = self.content.line_to_char(ln as usize - 1);
 
-			self.content.remove(ln as usize - 1);
-
-			if self.content.is_empty() {
-				self.content = vec![String::from("")];
+			if lines == 1 {
+				self.edit_chars(0, self.content.len_chars(), "");
+			} else if ln as usize == lines {
+				self.edit_chars(start - 1, self.content.len_chars(), "");
+			} else {
+				self.edit_chars(start, self.content.line_to_char(ln as usize), "");
 			}
 
 		}
 
-		return clamp(ln, 1, self.content.len() as Line);
+		return clamp(ln, 1, self.line_count() as Line);
 
 	}
 
-	/// delete current line
+	/// delete the lines of every cursor
 	pub fn del_line(&mut self) {
-		self.cursor.line = self.del_line_at(self.cursor.line);
+
+		let mut done = HashSet::new();
+
+		self.for_each_cursor(|buf, pos| {
+
+			if !done.insert(pos.line) {
+				return pos;
+			}
+
+			return Pos {
+				line: buf.del_line_at(pos.line),
+				.. pos
+			};
+
+		});
+
 	}
 
 	/// insert a line at secified position
@@ -405,20 +777,36 @@ impl Buffer {
 
 		self.push_undo();
 
-		if !self.modified {
-			self.redo_stack.clear();
-			self.modified = true;
+		if ln as usize > self.line_count() {
+			let end = self.content.len_chars();
+			self.edit_chars(end, end, "\n");
+		} else {
+			let start = self.content.line_to_char(ln as usize - 1);
+			self.edit_chars(start, start, "\n");
 		}
 
-		self.content.insert(ln as usize - 1, String::new());
-
-		return clamp(ln + 1, 1, self.content.len() as Line);
+		return clamp(ln + 1, 1, self.line_count() as Line);
 
 	}
 
-	/// insert a line at current cursor
+	/// insert a line at every cursor
 	pub fn insert_line(&mut self) {
-		self.cursor.line = self.insert_line_at(self.cursor.line);
+		self.for_each_cursor(|buf, pos| {
+			return Pos {
+				line: buf.insert_line_at(pos.line),
+				.. pos
+			};
+		});
+	}
+
+	/// check if a grapheme breaks words
+	pub(crate) fn is_break(&self, g: &str) -> bool {
+		return g.chars().next().map(|ch| self.conf.break_chars.contains(&ch)).unwrap_or(false);
+	}
+
+	/// check if a grapheme is part of a word
+	pub(crate) fn is_word(&self, g: &str) -> bool {
+		return !self.is_break(g) && !g.trim().is_empty();
 	}
 
 	// todo
@@ -426,12 +814,13 @@ impl Buffer {
 	pub fn next_word_at(&self, pos: Pos) -> Option<Pos> {
 
 		let line = self.get_line_at(pos.line)?;
+		let graphemes: Vec<&str> = line.graphemes(true).collect();
 
-		if pos.col < line.len() as Col {
+		if pos.col < graphemes.len() as Col {
 
-			for (i, ch) in line[pos.col as usize + 1..].char_indices() {
+			for (i, g) in graphemes[pos.col as usize + 1..].iter().enumerate() {
 
-				if self.conf.break_chars.contains(&ch) {
+				if self.is_break(g) {
 					return Some(Pos {
 						col: pos.col + 1 + i as Col,
 						.. pos
@@ -441,7 +830,7 @@ impl Buffer {
 			}
 
 			return Some(Pos {
-				col: line.len() as Col,
+				col: graphemes.len() as Col,
 				.. pos
 			});
 
@@ -451,6 +840,52 @@ impl Buffer {
 
 	}
 
+	/// get the start of the next word, or of the next run of punctuation, going to the next line at the line end
+	pub fn next_word_start_at(&self, pos: Pos) -> Option<Pos> {
+
+		let class = |g: &str| {
+			if g.trim().is_empty() {
+				return 0;
+			} else if self.is_break(g) {
+				return 2;
+			} else {
+				return 1;
+			}
+		};
+
+		let line = self.get_line_at(pos.line)?;
+		let graphemes: Vec<&str> = line.graphemes(true).collect();
+		let mut i = pos.col as usize - 1;
+
+		if let Some(g) = graphemes.get(i) {
+
+			let start = class(g);
+
+			while i < graphemes.len() && start != 0 && class(graphemes[i]) == start {
+				i += 1;
+			}
+
+			while i < graphemes.len() && class(graphemes[i]) == 0 {
+				i += 1;
+			}
+
+			if i < graphemes.len() {
+				return Some(Pos {
+					col: i as Col + 1,
+					.. pos
+				});
+			}
+
+		}
+
+		if (pos.line as usize) < self.line_count() {
+			return Some(self.line_start_at(Pos::new(pos.line + 1, 1)));
+		}
+
+		return None;
+
+	}
+
 	/// get next word position at current cursor
 	pub fn next_word(&self) -> Option<Pos> {
 		return self.next_word_at(self.cursor);
@@ -460,14 +895,15 @@ impl Buffer {
 	pub fn prev_word_at(&self, pos: Pos) -> Option<Pos> {
 
 		let line = self.get_line_at(pos.line)?;
+		let graphemes: Vec<&str> = line.graphemes(true).collect();
 
-		if pos.col <= line.len() as Col + 1 {
+		if pos.col <= graphemes.len() as Col + 1 {
 
-			let end = clamp(pos.col as i32 - 2, 0, line.len() as i32);
+			let end = clamp(pos.col as i32 - 2, 0, graphemes.len() as i32);
 
-			for (i, ch) in line[..end as usize].char_indices().rev() {
+			for (i, g) in graphemes[..end as usize].iter().enumerate().rev() {
 
-				if self.conf.break_chars.contains(&ch) {
+				if self.is_break(g) {
 					return Some(Pos {
 						col: i as Col + 2,
 						.. pos
@@ -495,12 +931,8 @@ impl Buffer {
 	/// copy the whole specified line
 	pub fn copy_line_at(&mut self, ln: Line) {
 
-		if let Some(content) = self.get_line_at(ln).map(Clone::clone) {
-			if let Ok(_) = self.clipboard.set_contents(content) {
-				// ...
-			} else {
-				// ...
-			}
+		if let Some(content) = self.get_line_at(ln) {
+			self.yank_text(&content, RegisterKind::Line);
 		}
 
 	}
@@ -510,20 +942,20 @@ impl Buffer {
 		self.copy_line_at(self.cursor.line);
 	}
 
-	/// paste at specified pos
+	/// paste the register in use at specified pos
 	pub fn paste_at(&mut self, pos: Pos) -> Pos {
 
-		if let Ok(content) = self.clipboard.get_contents() {
-			return self.insert_str_at(pos, &content);
+		if let Some(reg) = self.registers.get(self.register) {
+			return self.paste_register_at(pos, &reg);
 		}
 
 		return pos;
 
 	}
 
-	/// paste at current cursor
+	/// paste at every cursor
 	pub fn paste(&mut self) {
-		self.cursor = self.paste_at(self.cursor);
+		self.for_each_cursor(|buf, pos| buf.paste_at(pos));
 	}
 
 	/// returns the bound checked position of a cursor position
@@ -545,7 +977,7 @@ impl Buffer {
 
 		if let Some(line) = self.get_line_at(pos.line) {
 
-			let mut len = line.len() as Col;
+			let mut len = col_count(&line) as Col;
 
 			if len == 0 || self.mode == Mode::Insert {
 				len = len + 1;
@@ -562,7 +994,7 @@ impl Buffer {
 
 		}
 
-		let lines = self.content.len() as Line;
+		let lines = self.line_count() as Line;
 
 		if pos.line > lines && lines > 0 {
 			return self.cursor_bound(Pos {
@@ -580,55 +1012,49 @@ impl Buffer {
 		self.cursor = self.cursor_bound(pos);
 	}
 
-	/// adjust current cursor
+	/// adjust all cursors
 	pub fn adjust_cursor(&mut self) {
-		self.cursor = self.cursor_bound(self.cursor);
+		self.move_each(|_, pos| pos);
 	}
 
-	/// move current cursor left
+	/// move every cursor left
 	pub fn move_left(&mut self) {
-		self.move_to(Pos {
-			col: self.cursor.col - 1,
-			.. self.cursor
+		self.move_each(|_, pos| {
+			return Pos {
+				col: pos.col - 1,
+				.. pos
+			};
 		});
 	}
 
-	/// move current cursor right
+	/// move every cursor right
 	pub fn move_right(&mut self) {
-		self.move_to(Pos {
-			col: self.cursor.col + 1,
-			.. self.cursor
+		self.move_each(|_, pos| {
+			return Pos {
+				col: pos.col + 1,
+				.. pos
+			};
 		});
 	}
 
-	/// move current cursor up
+	/// move every cursor up
 	pub fn move_up(&mut self) {
-		self.move_to(Pos {
-			line: self.cursor.line - 1,
-			.. self.cursor
-		});
+		self.move_each(|buf, pos| buf.row_up_at(pos, 1).unwrap_or(pos));
 	}
 
-	/// move current cursor down
+	/// move every cursor down
 	pub fn move_down(&mut self) {
-		self.move_to(Pos {
-			line: self.cursor.line + 1,
-			.. self.cursor
-		});
+		self.move_each(|buf, pos| buf.row_down_at(pos, 1).unwrap_or(pos));
 	}
 
-	/// move to the previous word
+	/// move every cursor to the previous word
 	pub fn move_prev_word(&mut self) {
-		if let Some(pos) = self.prev_word() {
-			self.move_to(pos);
-		}
+		self.move_each(|buf, pos| buf.prev_word_at(pos).unwrap_or(pos));
 	}
 
-	/// move to the next word
+	/// move every cursor to the next word
 	pub fn move_next_word(&mut self) {
-		if let Some(pos) = self.next_word() {
-			self.move_to(pos);
-		}
+		self.move_each(|buf, pos| buf.next_word_at(pos).unwrap_or(pos));
 	}
 
 	/// get the position that a line starts, ignoring tabs and spaces
@@ -659,7 +1085,7 @@ impl Buffer {
 
 	/// line_start_at() with cursor movement
 	pub fn move_line_start(&mut self) {
-		self.cursor = self.line_start_at(self.cursor);
+		self.move_each(|buf, pos| buf.line_start_at(pos));
 	}
 
 	/// call move_line_start() and enter insert mode
@@ -675,7 +1101,7 @@ impl Buffer {
 	pub fn line_end_at(&self, mut pos: Pos) -> Pos {
 
 		if let Some(line) = self.get_line_at(pos.line) {
-			pos.col = line.len() as Col;
+			pos.col = col_count(&line) as Col;
 			return self.cursor_bound(pos);
 		}
 
@@ -685,7 +1111,7 @@ impl Buffer {
 
 	/// line_end_at() with cursor movement
 	pub fn move_line_end(&mut self) {
-		self.cursor = self.line_end_at(self.cursor);
+		self.move_each(|buf, pos| buf.line_end_at(pos));
 	}
 
 	/// call move_line_end() and enter insert mode
@@ -699,12 +1125,11 @@ impl Buffer {
 	/// insert a str at a cursor position
 	pub fn insert_str_at(&mut self, mut pos: Pos, text: &str) -> Pos {
 
-		if let Some(mut line) = self.get_line_at(pos.line).map(Clone::clone) {
+		if let Some(idx) = self.pos_to_char(pos) {
 
-			line.insert_str(pos.col as usize - 1, text);
 			self.push_undo();
-			self.set_line_at(pos.line, &line);
-			pos.col += text.len() as Col;
+			self.edit_chars(idx, idx, text);
+			pos = self.char_to_pos(idx + text.chars().count());
 
 			return self.cursor_bound(pos);
 
@@ -714,15 +1139,15 @@ impl Buffer {
 
 	}
 
-	/// insert_str_at() with cursor movement
+	/// insert_str_at() at every cursor
 	pub fn insert_str(&mut self, text: &str) {
-		self.cursor = self.insert_str_at(self.cursor, text);
+		self.for_each_cursor(|buf, pos| buf.insert_str_at(pos, text));
 	}
 
 	/// insert a char at a cursor position
-	pub fn insert_at(&mut self, mut pos: Pos, ch: char) -> Pos {
+	pub fn insert_at(&mut self, pos: Pos, ch: char) -> Pos {
 
-		if !ch.is_ascii() {
+		if !is_printable(ch) {
 			return pos;
 		}
 
@@ -730,23 +1155,21 @@ impl Buffer {
 			return pos;
 		}
 
-		if let Some(mut line) = self.get_line_at(pos.line).map(Clone::clone) {
+		if let Some(idx) = self.pos_to_char(pos) {
+
+			let mut text = ch.to_string();
 
 			if let Some(end_char) = self.filetype.pairs.get(&ch) {
-				line.insert(pos.col as usize - 1, ch);
-				line.insert(pos.col as usize, *end_char);
-			} else {
-				line.insert(pos.col as usize - 1, ch);
+				text.push(*end_char);
 			}
 
 			if self.conf.break_chars.contains(&ch) {
 				self.push_undo();
 			}
 
-			self.set_line_at(pos.line, &line);
-			pos.col += 1;
+			self.edit_chars(idx, idx, &text);
 
-			return self.cursor_bound(pos);
+			return self.cursor_bound(self.char_to_pos(idx + 1));
 
 		}
 
@@ -754,19 +1177,20 @@ impl Buffer {
 
 	}
 
-	/// insert_at() with cursor movement
+	/// insert_at() at every cursor
 	pub fn insert(&mut self, ch: char) {
-		self.cursor = self.insert_at(self.cursor, ch);
+		self.for_each_cursor(|buf, pos| buf.insert_at(pos, ch));
 	}
 
 	// todo
 	/// break and insert new line, calculating indent
 	pub fn break_line_at(&mut self, mut pos: Pos) -> Pos {
 
-		if let Some(line) = self.get_line_at(pos.line).map(Clone::clone) {
+		if let Some(line) = self.get_line_at(pos.line) {
 
-			let before = String::from(&line[0..pos.col as usize - 1]);
-			let after = String::from(&line[pos.col as usize - 1..line.len()]);
+			let byte = col_to_byte(&line, pos.col);
+			let before = String::from(&line[..byte]);
+			let after = String::from(&line[byte..]);
 			let indents = self.get_expected_indent_at(pos.line + 1).unwrap_or(0);
 
 			self.push_undo();
@@ -785,9 +1209,9 @@ impl Buffer {
 
 	}
 
-	/// break_line_at() with cursor movement
+	/// break_line_at() at every cursor
 	pub fn break_line(&mut self) {
-		self.cursor = self.break_line_at(self.cursor);
+		self.for_each_cursor(|buf, pos| buf.break_line_at(pos));
 	}
 
 	// todo: better matching
@@ -821,10 +1245,9 @@ impl Buffer {
 		}
 	}
 
-	/// comment current line
+	/// comment the lines of every cursor
 	pub fn comment(&mut self) {
-		self.comment_at(self.cursor.line);
-		self.cursor = self.cursor_bound(self.cursor);
+		self.for_each_line(|buf, ln| buf.comment_at(ln));
 	}
 
 	// todo: better matching
@@ -832,7 +1255,7 @@ impl Buffer {
 	pub fn uncomment_at(&mut self, ln: Line) {
 		if self.is_commented_at(ln) {
 			if let Some(comment) = self.filetype.comment.clone() {
-				if let Some(mut line) = self.get_line_at(ln).map(Clone::clone) {
+				if let Some(mut line) = self.get_line_at(ln) {
 					line.replace_range(0..comment.len() + 1, "");
 					self.set_line_at(ln, &line);
 				}
@@ -841,9 +1264,9 @@ impl Buffer {
 		self.cursor = self.cursor_bound(self.cursor);
 	}
 
-	/// uncomment current line
+	/// uncomment the lines of every cursor
 	pub fn uncomment(&mut self) {
-		self.uncomment_at(self.cursor.line);
+		self.for_each_line(|buf, ln| buf.uncomment_at(ln));
 	}
 
 	/// toggle comment at given line
@@ -855,15 +1278,44 @@ impl Buffer {
 		}
 	}
 
-	/// toggle comment at current line
+	/// toggle comment at the lines of every cursor
 	pub fn toggle_comment(&mut self) {
-		self.toggle_comment_at(self.cursor.line);
+		self.for_each_line(|buf, ln| buf.toggle_comment_at(ln));
+	}
+
+	/// run a line edit on every line between two lines (inclusive) as one undo step
+	pub fn edit_lines<F: FnMut(&mut Self, Line)>(&mut self, start: Line, end: Line, mut f: F) {
+
+		self.push_undo();
+		self.begin_undo_group();
+
+		for ln in start..=end {
+			f(self, ln);
+		}
+
+		self.end_undo_group();
+
+	}
+
+	/// comment the lines between two lines, or uncomment them if they're all commented
+	pub fn toggle_comment_lines(&mut self, start: Line, end: Line) {
+
+		let commented = (start..=end).all(|ln| self.is_commented_at(ln));
+
+		self.edit_lines(start, end, |buf, ln| {
+			if commented {
+				buf.uncomment_at(ln);
+			} else {
+				buf.comment_at(ln);
+			}
+		});
+
 	}
 
 	/// set a line's indent level
 	pub fn set_indent_at(&mut self, ln: Line, level: IndentLevel) {
 
-		if let Some(mut line) = self.get_line_at(ln).map(Clone::clone) {
+		if let Some(mut line) = self.get_line_at(ln) {
 
 			if let Some(indent) = self.get_indent_at(ln) {
 
@@ -883,20 +1335,20 @@ impl Buffer {
 
 	/// indent a line forward
 	pub fn indent_forward_at(&mut self, ln: Line) {
-		if let Some(mut line) = self.get_line_at(ln).map(Clone::clone) {
+		if let Some(mut line) = self.get_line_at(ln) {
 			line.insert(0, '\t');
 			self.set_line_at(ln, &line);
 		}
 	}
 
-	/// indent current line forward
+	/// indent the lines of every cursor forward
 	pub fn indent_forward(&mut self) {
-		self.indent_forward_at(self.cursor.line);
+		self.for_each_line(|buf, ln| buf.indent_forward_at(ln));
 	}
 
 	/// indent a line backwards
 	pub fn indent_backward_at(&mut self, ln: Line) {
-		if let Some(mut line) = self.get_line_at(ln).map(Clone::clone) {
+		if let Some(mut line) = self.get_line_at(ln) {
 			if line.chars().next() == Some('\t') {
 				line.replace_range(0..1, "");
 				self.set_line_at(ln, &line);
@@ -904,15 +1356,15 @@ impl Buffer {
 		}
 	}
 
-	/// indent current line backward
+	/// indent the lines of every cursor backward
 	pub fn indent_backward(&mut self) {
-		self.indent_backward_at(self.cursor.line);
+		self.for_each_line(|buf, ln| buf.indent_backward_at(ln));
 	}
 
 	/// get previous non empty line
 	pub fn get_prev_line(&self, ln: Line) -> Option<Line> {
 
-		if ln as usize <= self.content.len() {
+		if ln as usize <= self.line_count() {
 			// ...
 		}
 
@@ -950,7 +1402,7 @@ impl Buffer {
 		let mut indent = self.get_indent_at(ln - 1)?;
 
 		if let Some(forward_pat) = &self.filetype.indent_forward {
-			if forward_pat.is_match(prev_line) {
+			if forward_pat.is_match(&prev_line) {
 				indent += 1;
 			}
 		}
@@ -969,39 +1421,36 @@ impl Buffer {
 	/// delete char at specified position
 	pub fn del_at(&mut self, mut pos: Pos) -> Pos {
 
-		if let Some(mut line) = self.get_line_at(pos.line).map(Clone::clone) {
+		if let Some(idx) = self.pos_to_char(pos) {
 
-			let before = &line[0..pos.col as usize - 1];
+			if pos.col <= 1 {
 
-			if before.is_empty() {
+				if let Some(prev_line) = self.get_line_at(pos.line - 1) {
 
-				if let Some(mut prev_line) = self.get_line_at(pos.line - 1).map(Clone::clone) {
-
-					let col = prev_line.len() as Col + 1;
-
-					prev_line.push_str(&line);
-					self.del_line_at(pos.line);
-					self.set_line_at(pos.line - 1, &prev_line);
+					self.edit_chars(idx - 1, idx, "");
 					pos.line -= 1;
-					pos.col = col;
+					pos.col = col_count(&prev_line) as Col + 1;
 
 				}
 
 			} else {
 
-				if let Some(ch) = self.char_at(Pos::new(self.cursor.line, self.cursor.col - 1)) {
+				let prev = Pos::new(pos.line, pos.col - 1);
+				let start = self.pos_to_char(prev).unwrap_or(idx);
+				let mut end = idx;
+
+				if let Some(ch) = self.char_at(prev) {
 
-					let nch = self.char_at(self.cursor);
+					let nch = self.char_at(pos);
 					let end_char = self.filetype.pairs.get(&ch).map(Clone::clone);
 
 					if nch.is_some() && nch == end_char {
-						line.remove(pos.col as usize - 1);
+						end = self.pos_to_char(Pos::new(pos.line, pos.col + 1)).unwrap_or(idx);
 					}
 
 				}
 
-				line.remove(pos.col as usize - 2);
-				self.set_line_at(pos.line, &line);
+				self.edit_chars(start, end, "");
 				pos.col -= 1;
 
 			}
@@ -1014,14 +1463,15 @@ impl Buffer {
 
 	}
 
-	/// delete char at current cursor
+	/// delete char at every cursor
 	pub fn del(&mut self) {
-		self.cursor = self.del_at(self.cursor);
+		self.for_each_cursor(|buf, pos| buf.del_at(pos));
 	}
 
-	/// get char at position
+	/// get char at position, the first char of the grapheme if it has more
 	pub fn char_at(&self, pos: Pos) -> Option<char> {
-		return self.get_line_at(pos.line)?.chars().nth(pos.col as usize - 1);
+		let line = self.get_line_at(pos.line)?;
+		return grapheme_at(&line, pos.col)?.chars().next();
 	}
 
 	/// delete the word at specified position
@@ -1038,37 +1488,44 @@ impl Buffer {
 		return pos;
 	}
 
-	/// delete the word before the cursor
+	/// delete the word before every cursor
 	pub fn del_word(&mut self) {
-		let pos = self.del_word_at(self.cursor);
-		self.move_to(pos);
+		self.for_each_cursor(|buf, pos| buf.del_word_at(pos));
+	}
+
+	/// get the text in a range
+	pub fn get_range(&self, r: Range) -> Option<String> {
+		let (start, end) = self.range_to_chars(r)?;
+		return Some(String::from(self.content.slice(start..end)));
 	}
 
-	/// delete a range of text
+	/// delete a range of text, across any number of lines
 	pub fn del_range(&mut self, r: Range) -> Pos {
+		return self.replace_range(r, "");
+	}
 
-		let start = r.start;
-		let end = r.end;
+	/// replace a range of text, returns the position after the new text
+	pub fn replace_range(&mut self, r: Range, text: &str) -> Pos {
 
-		if start.line == end.line {
+		if let Some((start, end)) = self.range_to_chars(r) {
 
-			if let Some(line) = self.get_line_at(start.line) {
+			self.push_undo();
+			self.edit_chars(start, end, text);
 
-				let mut line = line.clone();
-				let start_col = clamp(start.col as usize - 1, 0, line.len());
-				let end_col = clamp(end.col as usize, 0, line.len());
+			return self.char_to_pos(start + text.chars().count());
 
-				self.push_undo();
-				line.replace_range(start_col..end_col, "");
-				self.set_line_at(start.line, &line);
+		}
 
-				return start;
+		return self.cursor;
 
-			}
+	}
 
-		}
+	/// copy a range of text
+	pub fn yank_range(&mut self, r: Range) {
 
-		return self.cursor;
+		if let Some(text) = self.get_range(r) {
+			self.yank_text(&text, RegisterKind::Char);
+		}
 
 	}
 
@@ -1076,11 +1533,11 @@ impl Buffer {
 	pub fn search_prev_inline_at(&self, pos: Pos, target: &str) -> Option<Pos> {
 
 		let line = self.get_line_at(pos.line)?;
-		let slice = line.get(0..pos.col as usize)?;
-		let index = slice.rfind(target)?;
+		let end = col_to_byte(&line, pos.col + 1);
+		let index = line[..end].rfind(target)?;
 
 		return Some(Pos {
-			col: index as Col + 1,
+			col: byte_to_col(&line, index),
 			.. pos
 		});
 
@@ -1090,11 +1547,11 @@ impl Buffer {
 	pub fn search_next_inline_at(&self, pos: Pos, target: &str) -> Option<Pos> {
 
 		let line = self.get_line_at(pos.line)?;
-		let slice = line.get(pos.col as usize..line.len())?;
-		let index = slice.find(target)?;
+		let start = col_to_byte(&line, pos.col + 1);
+		let index = line[start..].find(target)?;
 
 		return Some(Pos {
-			col: index as Col + 1 + pos.col,
+			col: byte_to_col(&line, start + index),
 			.. pos
 		});
 
@@ -1118,7 +1575,7 @@ impl Buffer {
 
 				let result = self.search_prev_inline_at(Pos {
 					line: i as Line + 1,
-					col: self.content[i].len() as Col,
+					col: self.get_line_at(i as Line + 1).map(|l| col_count(&l)).unwrap_or(0) as Col,
 				}, target);
 
 				if result.is_some() {
@@ -1147,11 +1604,11 @@ impl Buffer {
 
 		} else {
 
-			for i in pos.line as usize..self.content.len() {
+			for i in pos.line as usize..self.line_count() {
 
 				let result = self.search_next_inline_at(Pos {
 					line: i as Line + 1,
-					col: 1,
+					col: 0,
 				}, target);
 
 				if result.is_some() {
@@ -1176,37 +1633,209 @@ impl Buffer {
 		return self.search_next_at(self.cursor, target);
 	}
 
-	/// move to the prev search result
+	/// move every cursor to the prev search result
 	pub fn move_to_prev_inline(&mut self, target: &str) {
-		if let Some(pos) = self.search_prev_inline(target) {
-			self.move_to(pos);
-		}
+		self.move_each(|buf, pos| buf.search_prev_inline_at(pos, target).unwrap_or(pos));
 	}
 
-	/// move to the next search result
+	/// move every cursor to the next search result
 	pub fn move_to_next_inline(&mut self, target: &str) {
-		if let Some(pos) = self.search_next_inline(target) {
-			self.move_to(pos);
-		}
+		self.move_each(|buf, pos| buf.search_next_inline_at(pos, target).unwrap_or(pos));
 	}
 
-	/// move to the prev search result
+	/// move every cursor to the prev search result
 	pub fn move_to_prev(&mut self, target: &str) {
-		if let Some(pos) = self.search_prev(target) {
-			self.move_to(pos);
-		}
+		self.move_each(|buf, pos| buf.search_prev_at(pos, target).unwrap_or(pos));
 	}
 
-	/// move to the next search result
+	/// move every cursor to the next search result
 	pub fn move_to_next(&mut self, target: &str) {
-		if let Some(pos) = self.search_next(target) {
-			self.move_to(pos);
-		}
+		self.move_each(|buf, pos| buf.search_next_at(pos, target).unwrap_or(pos));
+	}
+
+	/// get all cursors, the main cursor first
+	pub fn cursors(&self) -> Vec<Pos> {
+
+		let mut cursors = vec![self.cursor];
+
+		cursors.extend(&self.child_cursors);
+
+		return cursors;
+
 	}
 
 	/// add a cursor
 	pub fn add_cursor(&mut self, pos: Pos) {
-		self.child_cursors.push(pos);
+		self.child_cursors.push(self.cursor_bound(pos));
+		self.merge_cursors();
+	}
+
+	/// remove child cursors that collide with another cursor
+	pub fn merge_cursors(&mut self) {
+
+		let mut seen = HashSet::new();
+
+		seen.insert(self.cursor);
+		self.child_cursors.retain(|pos| seen.insert(*pos));
+
+	}
+
+	/// move every cursor with a motion, then merge the ones that collide
+	pub fn move_each<F: Fn(&Self, Pos) -> Pos>(&mut self, f: F) {
+
+		self.cursor = self.cursor_bound(f(self, self.cursor));
+
+		let cursors = self.child_cursors
+			.iter()
+			.map(|pos| self.cursor_bound(f(self, *pos)))
+			.collect();
+
+		self.child_cursors = cursors;
+		self.merge_cursors();
+
+	}
+
+	/// run an edit at every cursor, from the last one to the first, shifting the cursors that were already edited by the edits that come before them
+	pub fn for_each_cursor<F: FnMut(&mut Self, Pos) -> Pos>(&mut self, mut f: F) {
+
+		self.merge_cursors();
+
+		let cursors = self.cursors();
+		let mut order: Vec<usize> = (0..cursors.len()).collect();
+
+		order.sort_by(|a, b| cursors[*b].cmp(&cursors[*a]));
+
+		self.tracked = cursors
+			.iter()
+			.map(|pos| self.pos_to_char(*pos).unwrap_or(0))
+			.collect();
+
+		for (n, i) in order.into_iter().enumerate() {
+
+			let pos = self.char_to_pos(self.tracked[i]);
+			let pos = f(self, pos);
+
+			self.tracked[i] = self.pos_to_char(pos).unwrap_or(self.tracked[i]);
+
+			// the first cursor starts the undo step, the rest join it
+			if n == 0 {
+				self.begin_undo_group();
+			}
+
+		}
+
+		self.end_undo_group();
+
+		let tracked = std::mem::replace(&mut self.tracked, Vec::new());
+		let mut cursors: Vec<Pos> = tracked
+			.into_iter()
+			.map(|idx| self.cursor_bound(self.char_to_pos(idx)))
+			.collect();
+
+		self.cursor = cursors.remove(0);
+		self.child_cursors = cursors;
+		self.merge_cursors();
+
+	}
+
+	/// run a line edit once for every line that has a cursor
+	pub fn for_each_line<F: FnMut(&mut Self, Line)>(&mut self, mut f: F) {
+
+		let mut done = HashSet::new();
+
+		self.for_each_cursor(|buf, pos| {
+
+			if done.insert(pos.line) {
+				f(buf, pos.line);
+			}
+
+			return pos;
+
+		});
+
+	}
+
+	/// get the range of the word at a position
+	pub fn word_range_at(&self, pos: Pos) -> Option<Range> {
+
+		let line = self.get_line_at(pos.line)?;
+		let graphemes: Vec<&str> = line.graphemes(true).collect();
+		let i = pos.col as usize - 1;
+
+		if i >= graphemes.len() || !self.is_word(graphemes[i]) {
+			return None;
+		}
+
+		let start = i - graphemes[..i].iter().rev().take_while(|g| self.is_word(g)).count();
+		let end = i + graphemes[i + 1..].iter().take_while(|g| self.is_word(g)).count();
+
+		return Some(Range {
+			start: Pos::new(pos.line, start as Col + 1),
+			end: Pos::new(pos.line, end as Col + 1),
+		});
+
+	}
+
+	/// get the word at a position
+	pub fn word_at(&self, pos: Pos) -> Option<String> {
+
+		let r = self.word_range_at(pos)?;
+		let line = self.get_line_at(pos.line)?;
+
+		return Some(String::from(&line[col_to_byte(&line, r.start.col)..col_to_byte(&line, r.end.col + 1)]));
+
+	}
+
+	/// add a cursor at the next appearence of the word under the main cursor, after the last added cursor
+	pub fn add_cursor_next_word(&mut self) {
+
+		let range = match self.word_range_at(self.cursor) {
+			Some(r) => r,
+			None => return,
+		};
+
+		let word = self.word_at(self.cursor).unwrap_or_default();
+		let offset = self.cursor.col - range.start.col;
+		let last = *self.child_cursors.last().unwrap_or(&self.cursor);
+		let from = self.word_range_at(last).map(|r| r.end).unwrap_or(last);
+		let found = self.search_next_at(from, &word)
+			.or_else(|| self.search_next_at(Pos::new(1, 0), &word));
+
+		if let Some(pos) = found {
+			self.add_cursor(Pos {
+				col: pos.col + offset,
+				.. pos
+			});
+		}
+
+	}
+
+	/// add a cursor above the topmost cursor
+	pub fn add_cursor_above(&mut self) {
+
+		if let Some(top) = self.cursors().into_iter().min() {
+			if top.line > 1 {
+				self.add_cursor(Pos {
+					line: top.line - 1,
+					.. top
+				});
+			}
+		}
+
+	}
+
+	/// add a cursor below the bottommost cursor
+	pub fn add_cursor_below(&mut self) {
+
+		if let Some(bottom) = self.cursors().into_iter().max() {
+			if (bottom.line as usize) < self.line_count() {
+				self.add_cursor(Pos {
+					line: bottom.line + 1,
+					.. bottom
+				});
+			}
+		}
+
 	}
 
 	/// reset states
@@ -1218,22 +1847,23 @@ impl Buffer {
 
 	}
 
+	/// get the display column of a position, with tabs and wide chars expanded
 	pub fn get_shifted_pos(&self, pos: Pos, width: u32) -> u32 {
 
 		let mut shift = 1;
 
 		if let Some(line) = self.get_line_at(pos.line) {
 
-			for (i, ch) in line.char_indices() {
+			for (i, g) in line.graphemes(true).enumerate() {
 
 				if i as u32 == pos.col - 1 {
 					return shift;
 				}
 
-				if ch == '\t' {
-					shift += width - (shift - 1) as u32 % width;
+				if g == "\t" {
+					shift += width - (shift - 1) % width;
 				} else {
-					shift += 1;
+					shift += grapheme_width(g);
 				}
 
 			}
@@ -1246,6 +1876,7 @@ impl Buffer {
 
 	}
 
+	/// get the column under a display column
 	pub fn get_unshifted_col(&self, pos: u32, ln: u32, width: u32) -> Col {
 
 		let mut shift = 1;
@@ -1253,19 +1884,20 @@ impl Buffer {
 
 		if let Some(line) = self.get_line_at(ln) {
 
-			for ch in line.chars() {
+			for g in line.graphemes(true) {
 
-				if pos <= shift {
+				let w = if g == "\t" {
+					width - (shift - 1) % width
+				} else {
+					grapheme_width(g)
+				};
+
+				if pos < shift + w {
 					return col;
 				}
 
-				if ch == '\t' {
-					shift += width - (shift - 1) as u32 % width;
-					col += 1;
-				} else {
-					shift += 1;
-					col += 1;
-				}
+				shift += w;
+				col += 1;
 
 			}
 
@@ -1279,6 +1911,21 @@ impl Buffer {
 
 }
 
+/// get where a char index ends up after an edit
+fn shift_char(idx: usize, edit: &Edit) -> usize {
+
+	let end = edit.at + edit.deleted_len();
+
+	if idx < edit.at {
+		return idx;
+	} else if idx >= end {
+		return idx - edit.deleted_len() + edit.inserted_len();
+	} else {
+		return edit.at;
+	}
+
+}
+
 pub fn clamp<N: PartialOrd>(x: N, min: N, max: N) -> N {
 
 	if min > max {
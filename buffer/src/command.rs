@@ -0,0 +1,937 @@
+// wengwengweng
+
+use std::fs;
+use std::fmt;
+use std::cmp;
+use std::path::PathBuf;
+use std::collections::HashSet;
+
+use super::*;
+
+/// where an address starts from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddrBase {
+	/// a line number, 0 means before the first line
+	Line(Line),
+	/// . the line of the cursor
+	Current,
+	/// $ the last line
+	Last,
+	/// 'a the line of a mark, '< and '> are the first and last line of the last selection
+	Mark(char),
+}
+
+/// a line address like 10, ., $+2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Address {
+	pub base: AddrBase,
+	pub offset: i64,
+}
+
+/// a line range in front of a command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CmdRange {
+	pub start: Address,
+	pub end: Address,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cmd {
+	/// only a range, goes to its last line
+	Goto,
+	Write {
+		path: Option<PathBuf>,
+		quit: bool,
+		/// write even if the file changed on disk, or over another file that exists
+		force: bool,
+	},
+	Quit {
+		force: bool,
+	},
+	Edit {
+		path: PathBuf,
+	},
+	Read {
+		path: PathBuf,
+	},
+	Delete,
+	Sort {
+		reverse: bool,
+		unique: bool,
+	},
+	Substitute {
+		pattern: String,
+		replace: String,
+		all: bool,
+		ignore_case: Option<bool>,
+		confirm: bool,
+	},
+	Global {
+		pattern: String,
+		invert: bool,
+		cmd: Box<Cmd>,
+	},
+	/// change how the file is written, or show it with no option
+	Set(Option<FormatOpt>),
+	/// switch the filetype, or show it with no name
+	Filetype(Option<String>),
+	/// load the unsaved changes found in a swap file
+	Recover,
+	/// throw away a swap file
+	DeleteSwap,
+	/// read the file again, dropping unsaved edits
+	Reload,
+	/// merge unsaved edits with changes made on disk
+	Merge,
+	/// go back n undo states in time, across branches
+	Earlier(usize),
+	/// go forward n undo states in time, across branches
+	Later(usize),
+	/// undo once, or go to an undo state by id
+	Undo(Option<usize>),
+}
+
+/// a parsed command line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+	pub range: Option<CmdRange>,
+	pub cmd: Cmd,
+}
+
+/// what the view should do after a command ran
+#[derive(Debug)]
+pub enum CmdAction {
+	None,
+	Quit,
+	Edit(PathBuf),
+	/// a substitution waiting for every match to be confirmed
+	Confirm(Substitution),
+	Message(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CmdError {
+	UnknownCommand(String),
+	InvalidRange(String),
+	InvalidPattern(String),
+	MissingArgument(&'static str),
+	TrailingChars(String),
+	NoSelection,
+	NoMark(char),
+	NoSwap,
+	ChangedOnDisk,
+	InvalidOption(String),
+	UnknownFiletype(String),
+	CantEncode(char, Encoding),
+	Unsaved,
+	IO(PathBuf, String),
+}
+
+impl From<Error> for CmdError {
+	fn from(e: Error) -> Self {
+		return CmdError::IO(e.path().to_path_buf(), e.reason());
+	}
+}
+
+impl fmt::Display for CmdError {
+
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+		return match self {
+			CmdError::UnknownCommand(name) => write!(f, "unknown command: {}", name),
+			CmdError::InvalidRange(range) => write!(f, "invalid range: {}", range),
+			CmdError::InvalidPattern(e) => write!(f, "invalid pattern: {}", e),
+			CmdError::MissingArgument(arg) => write!(f, "missing argument: {}", arg),
+			CmdError::TrailingChars(s) => write!(f, "trailing characters: {}", s),
+			CmdError::NoSelection => write!(f, "no previous selection"),
+			CmdError::NoMark(c) => write!(f, "mark not set: {}", c),
+			CmdError::ChangedOnDisk => write!(f, "file changed on disk (:merge, :reload, or add ! to overwrite)"),
+			CmdError::NoSwap => write!(f, "no swap file to recover"),
+			CmdError::InvalidOption(opt) => write!(f, "invalid option: {}", opt),
+			CmdError::UnknownFiletype(name) => write!(f, "unknown filetype: {}", name),
+			CmdError::CantEncode(ch, enc) => write!(f, "can't convert {:?} to {}", ch, enc.name()),
+			CmdError::Unsaved => write!(f, "unsaved changes (add ! to override)"),
+			CmdError::IO(path, e) => write!(f, "{}: {}", path.display(), e),
+		};
+
+	}
+
+}
+
+/// a cursor over the command line text
+struct Parser<'a> {
+	text: &'a str,
+	pos: usize,
+}
+
+impl<'a> Parser<'a> {
+
+	fn new(text: &'a str) -> Self {
+		return Self {
+			text: text,
+			pos: 0,
+		};
+	}
+
+	fn rest(&self) -> &'a str {
+		return &self.text[self.pos..];
+	}
+
+	fn peek(&self) -> Option<char> {
+		return self.rest().chars().next();
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let ch = self.peek()?;
+		self.pos += ch.len_utf8();
+		return Some(ch);
+	}
+
+	fn eat(&mut self, ch: char) -> bool {
+		if self.peek() == Some(ch) {
+			self.bump();
+			return true;
+		}
+		return false;
+	}
+
+	fn skip_space(&mut self) {
+		while self.peek().map(char::is_whitespace).unwrap_or(false) {
+			self.bump();
+		}
+	}
+
+	fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+		let start = self.pos;
+		while self.peek().map(&f).unwrap_or(false) {
+			self.bump();
+		}
+		return &self.text[start..self.pos];
+	}
+
+	fn number(&mut self) -> Option<i64> {
+		return self.take_while(|c| c.is_ascii_digit()).parse().ok();
+	}
+
+	fn address(&mut self) -> Result<Option<Address>, CmdError> {
+
+		let start = self.pos;
+
+		let base = match self.peek() {
+			Some('.') => {
+				self.bump();
+				Some(AddrBase::Current)
+			},
+			Some('$') => {
+				self.bump();
+				Some(AddrBase::Last)
+			},
+			Some('\'') => {
+				self.bump();
+				match self.bump() {
+					Some(c) => Some(AddrBase::Mark(c)),
+					None => return Err(CmdError::InvalidRange(String::from(&self.text[start..self.pos]))),
+				}
+			},
+			Some(c) if c.is_ascii_digit() => self.number().map(|n| AddrBase::Line(n as Line)),
+			_ => None,
+		};
+
+		let mut offset = 0;
+		let mut has_offset = false;
+
+		while let Some(sign) = self.peek().filter(|c| *c == '+' || *c == '-') {
+			self.bump();
+			let n = self.number().unwrap_or(1);
+			offset += if sign == '+' { n } else { -n };
+			has_offset = true;
+		}
+
+		return Ok(match base {
+			Some(base) => Some(Address {
+				base: base,
+				offset: offset,
+			}),
+			None if has_offset => Some(Address {
+				base: AddrBase::Current,
+				offset: offset,
+			}),
+			None => None,
+		});
+
+	}
+
+	fn range(&mut self) -> Result<Option<CmdRange>, CmdError> {
+
+		if self.eat('%') {
+			return Ok(Some(CmdRange {
+				start: Address {
+					base: AddrBase::Line(1),
+					offset: 0,
+				},
+				end: Address {
+					base: AddrBase::Last,
+					offset: 0,
+				},
+			}));
+		}
+
+		let start = match self.address()? {
+			Some(addr) => addr,
+			None => return Ok(None),
+		};
+
+		if self.eat(',') {
+
+			let end = self.address()?
+				.ok_or_else(|| CmdError::InvalidRange(String::from(self.text.trim())))?;
+
+			return Ok(Some(CmdRange {
+				start: start,
+				end: end,
+			}));
+
+		}
+
+		return Ok(Some(CmdRange {
+			start: start,
+			end: start,
+		}));
+
+	}
+
+	/// read a part up to an unescaped delimiter, a \ before the delimiter is dropped
+	fn delimited(&mut self, delim: char) -> String {
+
+		let mut part = String::new();
+
+		while let Some(ch) = self.bump() {
+			if ch == delim {
+				break;
+			} else if ch == '\\' && self.peek() == Some(delim) {
+				part.push(delim);
+				self.bump();
+			} else {
+				part.push(ch);
+			}
+		}
+
+		return part;
+
+	}
+
+	fn path(&mut self) -> Result<PathBuf, CmdError> {
+
+		self.skip_space();
+
+		let path = self.rest().trim();
+
+		if path.is_empty() {
+			return Err(CmdError::MissingArgument("file name"));
+		}
+
+		self.pos = self.text.len();
+
+		return Ok(PathBuf::from(path));
+
+	}
+
+	fn end(&mut self) -> Result<(), CmdError> {
+
+		self.skip_space();
+
+		if !self.rest().is_empty() {
+			return Err(CmdError::TrailingChars(String::from(self.rest())));
+		}
+
+		return Ok(());
+
+	}
+
+	fn cmd(&mut self) -> Result<Cmd, CmdError> {
+
+		self.skip_space();
+
+		let name = self.take_while(|c| c.is_ascii_alphabetic());
+		let force = self.eat('!');
+
+		let cmd = match name {
+
+			"" => Cmd::Goto,
+
+			"w" | "write" | "wq" | "x" => {
+
+				self.skip_space();
+
+				let path = if self.rest().is_empty() {
+					None
+				} else {
+					Some(self.path()?)
+				};
+
+				Cmd::Write {
+					path: path,
+					quit: name == "wq" || name == "x",
+					force: force,
+				}
+
+			},
+
+			"q" | "quit" => Cmd::Quit {
+				force: force,
+			},
+
+			"e" | "edit" => Cmd::Edit {
+				path: self.path()?,
+			},
+
+			"r" | "read" => Cmd::Read {
+				path: self.path()?,
+			},
+
+			"d" | "delete" => Cmd::Delete,
+
+			"sort" => {
+
+				self.skip_space();
+
+				let unique = self.eat('u');
+
+				Cmd::Sort {
+					reverse: force,
+					unique: unique,
+				}
+
+			},
+
+			"s" | "substitute" => {
+
+				let delim = self.bump().ok_or(CmdError::MissingArgument("pattern"))?;
+				let pattern = self.delimited(delim);
+				let replace = self.delimited(delim);
+				let mut all = false;
+				let mut ignore_case = None;
+				let mut confirm = false;
+
+				while let Some(flag) = self.peek().filter(|c| c.is_ascii_alphabetic()) {
+					match flag {
+						'g' => all = true,
+						'i' => ignore_case = Some(true),
+						'I' => ignore_case = Some(false),
+						'c' => confirm = true,
+						_ => return Err(CmdError::TrailingChars(String::from(self.rest()))),
+					}
+					self.bump();
+				}
+
+				Cmd::Substitute {
+					pattern: pattern,
+					replace: replace,
+					all: all,
+					ignore_case: ignore_case,
+					confirm: confirm,
+				}
+
+			},
+
+			"g" | "global" | "v" | "vglobal" => {
+
+				let delim = self.bump().ok_or(CmdError::MissingArgument("pattern"))?;
+				let pattern = self.delimited(delim);
+				let cmd = self.cmd()?;
+
+				if cmd == Cmd::Goto {
+					return Err(CmdError::MissingArgument("command"));
+				}
+
+				Cmd::Global {
+					pattern: pattern,
+					invert: force || name.starts_with('v'),
+					cmd: Box::new(cmd),
+				}
+
+			},
+
+			"recover" => Cmd::Recover,
+
+			"delswap" => Cmd::DeleteSwap,
+
+			"reload" => Cmd::Reload,
+
+			"merge" => Cmd::Merge,
+
+			"earlier" | "ea" => {
+				self.skip_space();
+				Cmd::Earlier(self.number().unwrap_or(1) as usize)
+			},
+
+			"later" | "lat" => {
+				self.skip_space();
+				Cmd::Later(self.number().unwrap_or(1) as usize)
+			},
+
+			"u" | "undo" => {
+				self.skip_space();
+				Cmd::Undo(self.number().map(|n| n as usize))
+			},
+
+			"set" | "se" => {
+
+				self.skip_space();
+
+				let opt = self.take_while(|c| !c.is_whitespace());
+
+				if opt.is_empty() {
+					Cmd::Set(None)
+				} else if opt == "ft" || opt == "filetype" {
+					Cmd::Filetype(None)
+				} else if let Some(ft) = opt.strip_prefix("ft=").or_else(|| opt.strip_prefix("filetype=")) {
+					Cmd::Filetype(Some(String::from(ft)))
+				} else {
+					Cmd::Set(Some(FormatOpt::parse(opt).ok_or_else(|| CmdError::InvalidOption(String::from(opt)))?))
+				}
+
+			},
+
+			"setf" | "setfiletype" => {
+
+				self.skip_space();
+
+				let ft = self.take_while(|c| !c.is_whitespace());
+
+				if ft.is_empty() {
+					return Err(CmdError::MissingArgument("filetype"));
+				}
+
+				Cmd::Filetype(Some(String::from(ft)))
+
+			},
+
+			_ => return Err(CmdError::UnknownCommand(String::from(name))),
+
+		};
+
+		self.end()?;
+
+		return Ok(cmd);
+
+	}
+
+}
+
+/// parse a command line like "10,20s/a/b/g"
+pub fn parse_command(text: &str) -> Result<Command, CmdError> {
+
+	let mut parser = Parser::new(text);
+
+	parser.skip_space();
+
+	let range = parser.range()?;
+	let cmd = parser.cmd()?;
+
+	return Ok(Command {
+		range: range,
+		cmd: cmd,
+	});
+
+}
+
+impl Buffer {
+
+	/// get the command line being typed in command mode
+	pub fn command_text(&self) -> Option<&str> {
+		if let Mode::Command { text } = &self.mode {
+			return Some(text);
+		}
+		return None;
+	}
+
+	/// type a char into the command line
+	pub fn command_input(&mut self, ch: char) {
+		if let Mode::Command { text } = &mut self.mode {
+			if is_printable(ch) {
+				text.push(ch);
+			}
+		}
+	}
+
+	/// delete the last char of the command line, leaving command mode if it's empty
+	pub fn command_del(&mut self) {
+		if let Mode::Command { text } = &mut self.mode {
+			if text.pop().is_none() {
+				self.mode = Mode::Normal;
+			}
+		}
+	}
+
+	/// leave command mode without running anything
+	pub fn command_cancel(&mut self) {
+		if let Mode::Command { .. } = self.mode {
+			self.mode = Mode::Normal;
+		}
+	}
+
+	/// run the command line and leave command mode
+	pub fn command_confirm(&mut self) -> Result<CmdAction, CmdError> {
+
+		let text = match self.command_text() {
+			Some(text) => String::from(text),
+			None => return Ok(CmdAction::None),
+		};
+
+		self.mode = Mode::Normal;
+
+		return self.run_command(&text);
+
+	}
+
+	/// parse and run a command line
+	pub fn run_command(&mut self, text: &str) -> Result<CmdAction, CmdError> {
+
+		if text.trim().is_empty() {
+			return Ok(CmdAction::None);
+		}
+
+		let cmd = parse_command(text)?;
+
+		return self.exec_command(&cmd);
+
+	}
+
+	/// get the line of an address, 0 is allowed for commands that insert below a line
+	fn resolve_addr(&self, addr: Address) -> Result<Line, CmdError> {
+
+		let last = self.line_count() as i64;
+
+		let base = match addr.base {
+			AddrBase::Line(ln) => ln as i64,
+			AddrBase::Current => self.cursor.line as i64,
+			AddrBase::Last => last,
+			AddrBase::Mark(c) => match self.mark(c) {
+				Some(pos) => pos.line as i64,
+				None if c == '<' || c == '>' => return Err(CmdError::NoSelection),
+				None => return Err(CmdError::NoMark(c)),
+			},
+		};
+
+		let ln = base + addr.offset;
+
+		if ln < 0 || ln > last {
+			return Err(CmdError::InvalidRange(format!("{}", ln)));
+		}
+
+		return Ok(ln as Line);
+
+	}
+
+	/// get the lines of a range, or the default lines when there's no range
+	fn resolve_range(&self, range: Option<CmdRange>, default: (Line, Line)) -> Result<(Line, Line), CmdError> {
+
+		let range = match range {
+			Some(range) => range,
+			None => return Ok(default),
+		};
+
+		let start = self.resolve_addr(range.start)?;
+		let end = self.resolve_addr(range.end)?;
+
+		if start > end {
+			return Err(CmdError::InvalidRange(format!("{},{}", start, end)));
+		}
+
+		return Ok((start, end));
+
+	}
+
+	fn make_query(&self, pattern: &str, ignore_case: Option<bool>) -> Result<Query, CmdError> {
+
+		let pattern = if pattern.is_empty() {
+			match &self.last_search {
+				Some(q) => q.pattern.clone(),
+				None => return Err(CmdError::MissingArgument("pattern")),
+			}
+		} else {
+			String::from(pattern)
+		};
+
+		let mut opts = self.search_opts.clone();
+		let mut src = pattern;
+
+		match ignore_case {
+			Some(true) => {
+				if !opts.regex {
+					src = regex::escape(&src);
+					opts.regex = true;
+				}
+				src = format!("(?i){}", src);
+			},
+			Some(false) => opts.smart_case = false,
+			None => {},
+		}
+
+		return Query::new(&src, opts)
+			.map_err(|e| CmdError::InvalidPattern(format!("{}", e)));
+
+	}
+
+	/// run a parsed command
+	pub fn exec_command(&mut self, cmd: &Command) -> Result<CmdAction, CmdError> {
+
+		let cur = self.cursor.line;
+		let last = self.line_count() as Line;
+
+		match &cmd.cmd {
+
+			Cmd::Goto => {
+				let (_, ln) = self.resolve_range(cmd.range, (cur, cur))?;
+				self.push_jump(self.cursor);
+				self.move_to(Pos::new(cmp::max(ln, 1), 1));
+			},
+
+			Cmd::Write { path, quit, force } => {
+
+				match path {
+					Some(path) => self.write_as(path.clone(), *force)?,
+					None => {
+						if !force && self.disk_changed() {
+							return Err(CmdError::ChangedOnDisk);
+						}
+						self.write(*force)?;
+					},
+				}
+
+				if *quit {
+					return Ok(CmdAction::Quit);
+				}
+
+				return Ok(CmdAction::Message(format!("written {}", self.path.display())));
+
+			},
+
+			Cmd::Quit { force } => {
+
+				if self.modified() && !force {
+					return Err(CmdError::Unsaved);
+				}
+
+				self.delete_swap();
+
+				return Ok(CmdAction::Quit);
+
+			},
+
+			Cmd::Edit { path } => {
+				return Ok(CmdAction::Edit(path.clone()));
+			},
+
+			Cmd::Read { path } => {
+
+				let (_, ln) = self.resolve_range(cmd.range, (cur, cur))?;
+				let text = fs::read_to_string(path)
+					.map_err(|e| Error::from_io(path, FileOp::Read, e))?;
+				let text = text.trim_end_matches('\n');
+
+				if ln == 0 {
+					self.insert_str_at(Pos::new(1, 1), &format!("{}\n", text));
+				} else {
+					self.insert_str_at(Pos::new(ln, self.line_len(ln) + 1), &format!("\n{}", text));
+				}
+
+				self.move_to(Pos::new(ln + 1, 1));
+
+			},
+
+			Cmd::Delete => {
+
+				let (start, end) = self.resolve_range(cmd.range, (cur, cur))?;
+
+				self.push_undo();
+				self.begin_undo_group();
+
+				for ln in (cmp::max(start, 1)..=end).rev() {
+					self.del_line_at(ln);
+				}
+
+				self.end_undo_group();
+				self.move_to(Pos::new(cmp::max(start, 1), 1));
+
+			},
+
+			Cmd::Sort { reverse, unique } => {
+
+				let (start, end) = self.resolve_range(cmd.range, (1, last))?;
+				let start = cmp::max(start, 1);
+				let mut lines: Vec<String> = (start..=end)
+					.filter_map(|ln| self.get_line_at(ln))
+					.collect();
+
+				lines.sort();
+
+				if *reverse {
+					lines.reverse();
+				}
+
+				if *unique {
+					lines.dedup();
+				}
+
+				self.replace_range(Range {
+					start: Pos::new(start, 1),
+					end: Pos::new(end, self.line_len(end)),
+				}, &lines.join("\n"));
+
+				self.move_to(Pos::new(start, 1));
+
+			},
+
+			Cmd::Substitute { pattern, replace, all, ignore_case, confirm } => {
+
+				let (start, end) = self.resolve_range(cmd.range, (cur, cur))?;
+				let query = self.make_query(pattern, *ignore_case)?;
+				let scope = SubScope::Lines(cmp::max(start, 1), end);
+				let mut sub = self.substitute_interactive(&query, replace, scope);
+
+				if !all {
+					let mut seen = HashSet::new();
+					sub.retain(|e| seen.insert(e.at.line));
+				}
+
+				if *confirm {
+					return Ok(CmdAction::Confirm(sub));
+				}
+
+				sub.accept_rest();
+
+				let count = self.apply_substitution(&sub);
+
+				return Ok(CmdAction::Message(format!("{} substitutions", count)));
+
+			},
+
+			Cmd::Global { pattern, invert, cmd: sub_cmd } => {
+
+				let (start, end) = self.resolve_range(cmd.range, (1, last))?;
+				let query = self.make_query(pattern, None)?;
+				let lines: Vec<Line> = (cmp::max(start, 1)..=end)
+					.filter(|ln| {
+						let line = self.get_line_at(*ln).unwrap_or_default();
+						return query.regex().is_match(&line) != *invert;
+					})
+					.collect();
+
+				self.push_undo();
+				self.begin_undo_group();
+
+				let mut result = Ok(CmdAction::None);
+
+				for ln in lines.into_iter().rev() {
+
+					let line_addr = Address {
+						base: AddrBase::Line(ln),
+						offset: 0,
+					};
+
+					result = self.exec_command(&Command {
+						range: Some(CmdRange {
+							start: line_addr,
+							end: line_addr,
+						}),
+						cmd: (**sub_cmd).clone(),
+					});
+
+					if result.is_err() {
+						break;
+					}
+
+				}
+
+				self.end_undo_group();
+
+				return result.map(|_| CmdAction::None);
+
+			},
+
+			Cmd::Set(opt) => {
+
+				if let Some(opt) = opt {
+					self.set_format(*opt).map_err(|ch| match opt {
+						FormatOpt::Encoding(enc) => CmdError::CantEncode(ch, *enc),
+						_ => CmdError::CantEncode(ch, self.format.encoding),
+					})?;
+				}
+
+				return Ok(CmdAction::Message(format!("{} {}", self.filetype.name, self.format)));
+
+			},
+
+			Cmd::Filetype(name) => {
+
+				if let Some(name) = name {
+					if !self.set_filetype(name) {
+						return Err(CmdError::UnknownFiletype(name.clone()));
+					}
+				}
+
+				return Ok(CmdAction::Message(format!("filetype {}", self.filetype.name)));
+
+			},
+
+			Cmd::Recover => {
+
+				if !self.recover_swap() {
+					return Err(CmdError::NoSwap);
+				}
+
+				return Ok(CmdAction::Message(String::from("recovered unsaved changes, :w to keep them")));
+
+			},
+
+			Cmd::DeleteSwap => {
+				self.delete_swap();
+			},
+
+			Cmd::Reload => {
+				self.reload()?;
+			},
+
+			Cmd::Earlier(n) => {
+				self.earlier(*n);
+				return Ok(CmdAction::Message(self.undo_state()));
+			},
+
+			Cmd::Later(n) => {
+				self.later(*n);
+				return Ok(CmdAction::Message(self.undo_state()));
+			},
+
+			Cmd::Undo(id) => {
+
+				match id {
+					Some(id) => self.goto_state(*id),
+					None => self.undo(),
+				}
+
+				return Ok(CmdAction::Message(self.undo_state()));
+
+			},
+
+			Cmd::Merge => {
+
+				let conflicts = self.merge_disk()?;
+
+				if conflicts > 0 {
+					return Ok(CmdAction::Message(format!("merged with {} conflicts", conflicts)));
+				}
+
+				return Ok(CmdAction::Message(String::from("merged")));
+
+			},
+
+		}
+
+		return Ok(CmdAction::None);
+
+	}
+
+}
+
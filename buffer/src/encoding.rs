@@ -0,0 +1,352 @@
+// wengwengweng
+
+use std::fmt;
+
+use super::*;
+
+const UTF8_BOM: [u8; 3] = [0xef, 0xbb, 0xbf];
+const UTF16LE_BOM: [u8; 2] = [0xff, 0xfe];
+const UTF16BE_BOM: [u8; 2] = [0xfe, 0xff];
+
+/// how lines end in a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+	/// \n
+	LF,
+	/// \r\n
+	CRLF,
+	/// \r
+	CR,
+}
+
+impl LineEnding {
+
+	pub fn as_str(&self) -> &'static str {
+		return match self {
+			LineEnding::LF => "\n",
+			LineEnding::CRLF => "\r\n",
+			LineEnding::CR => "\r",
+		};
+	}
+
+	/// get a line ending by name, unix / dos / mac or lf / crlf / cr
+	pub fn from_name(name: &str) -> Option<Self> {
+		return match name.to_ascii_lowercase().as_str() {
+			"unix" | "lf" => Some(LineEnding::LF),
+			"dos" | "crlf" => Some(LineEnding::CRLF),
+			"mac" | "cr" => Some(LineEnding::CR),
+			_ => None,
+		};
+	}
+
+	pub fn name(&self) -> &'static str {
+		return match self {
+			LineEnding::LF => "unix",
+			LineEnding::CRLF => "dos",
+			LineEnding::CR => "mac",
+		};
+	}
+
+}
+
+/// how text is stored in a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+	Utf8,
+	Utf16LE,
+	Utf16BE,
+	/// every byte is the char with the same code, ISO-8859-1
+	Latin1,
+}
+
+impl Encoding {
+
+	/// get an encoding by name, like utf-8, utf-16le or latin1
+	pub fn from_name(name: &str) -> Option<Self> {
+		return match name.to_ascii_lowercase().replace('_', "-").as_str() {
+			"utf-8" | "utf8" => Some(Encoding::Utf8),
+			"utf-16" | "utf16" | "utf-16le" | "utf16le" => Some(Encoding::Utf16LE),
+			"utf-16be" | "utf16be" => Some(Encoding::Utf16BE),
+			"latin1" | "latin-1" | "iso-8859-1" => Some(Encoding::Latin1),
+			_ => None,
+		};
+	}
+
+	pub fn name(&self) -> &'static str {
+		return match self {
+			Encoding::Utf8 => "utf-8",
+			Encoding::Utf16LE => "utf-16le",
+			Encoding::Utf16BE => "utf-16be",
+			Encoding::Latin1 => "latin1",
+		};
+	}
+
+	/// if a char can be stored in this encoding
+	pub fn can_encode(&self, ch: char) -> bool {
+		return match self {
+			Encoding::Latin1 => (ch as u32) <= 0xff,
+			_ => true,
+		};
+	}
+
+}
+
+/// what a file looked like on disk, kept to write it back the same way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileFormat {
+	pub line_ending: LineEnding,
+	pub encoding: Encoding,
+	/// the file starts with a byte order mark
+	pub bom: bool,
+	/// the last line ends with a line ending
+	pub final_newline: bool,
+	/// the file has no lines, it stays that way until text is typed even with final_newline
+	pub empty: bool,
+}
+
+impl Default for FileFormat {
+	fn default() -> Self {
+		return Self {
+			line_ending: LineEnding::LF,
+			encoding: Encoding::Utf8,
+			bom: false,
+			final_newline: true,
+			empty: false,
+		};
+	}
+}
+
+impl fmt::Display for FileFormat {
+
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+		write!(f, "{} {}", self.encoding.name(), self.line_ending.name())?;
+
+		if self.bom {
+			write!(f, " bom")?;
+		}
+
+		if !self.final_newline {
+			write!(f, " noeol")?;
+		}
+
+		return Ok(());
+
+	}
+
+}
+
+/// a file format option changed with :set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatOpt {
+	LineEnding(LineEnding),
+	Encoding(Encoding),
+	Bom(bool),
+	FinalNewline(bool),
+}
+
+impl FormatOpt {
+
+	/// parse an option like ff=dos, fenc=latin1, bomb, noeol
+	pub fn parse(s: &str) -> Option<Self> {
+
+		if let Some(i) = s.find('=') {
+
+			let (name, value) = (&s[..i], &s[i + 1..]);
+
+			return match name {
+				"ff" | "fileformat" => LineEnding::from_name(value).map(FormatOpt::LineEnding),
+				"fenc" | "fileencoding" => Encoding::from_name(value).map(FormatOpt::Encoding),
+				_ => None,
+			};
+
+		}
+
+		return match s {
+			"bomb" => Some(FormatOpt::Bom(true)),
+			"nobomb" => Some(FormatOpt::Bom(false)),
+			"eol" => Some(FormatOpt::FinalNewline(true)),
+			"noeol" => Some(FormatOpt::FinalNewline(false)),
+			_ => None,
+		};
+
+	}
+
+}
+
+/// guess the encoding of some bytes, returns it with the length of the bom
+fn detect_encoding(bytes: &[u8]) -> (Encoding, usize) {
+
+	if bytes.starts_with(&UTF8_BOM) {
+		return (Encoding::Utf8, UTF8_BOM.len());
+	}
+
+	if bytes.starts_with(&UTF16LE_BOM) {
+		return (Encoding::Utf16LE, UTF16LE_BOM.len());
+	}
+
+	if bytes.starts_with(&UTF16BE_BOM) {
+		return (Encoding::Utf16BE, UTF16BE_BOM.len());
+	}
+
+	if std::str::from_utf8(bytes).is_ok() {
+		return (Encoding::Utf8, 0);
+	}
+
+	// utf-16 without a bom, ascii text has a zero in every other byte
+	if bytes.len().is_multiple_of(2) {
+
+		let zeros = |start: usize| bytes.iter().skip(start).step_by(2).filter(|b| **b == 0).count();
+		let half = bytes.len() / 2;
+
+		if zeros(1) * 2 > half && zeros(0) == 0 {
+			return (Encoding::Utf16LE, 0);
+		}
+
+		if zeros(0) * 2 > half && zeros(1) == 0 {
+			return (Encoding::Utf16BE, 0);
+		}
+
+	}
+
+	return (Encoding::Latin1, 0);
+
+}
+
+fn decode_utf16(bytes: &[u8], from: fn([u8; 2]) -> u16) -> Option<String> {
+
+	if !bytes.len().is_multiple_of(2) {
+		return None;
+	}
+
+	let units: Vec<u16> = bytes
+		.chunks(2)
+		.map(|c| from([c[0], c[1]]))
+		.collect();
+
+	return String::from_utf16(&units).ok();
+
+}
+
+/// get the line ending used the most, \n if there's no line break
+fn detect_line_ending(text: &str) -> LineEnding {
+
+	let crlf = text.matches("\r\n").count();
+	let cr = text.matches('\r').count() - crlf;
+	let lf = text.matches('\n').count() - crlf;
+
+	if crlf > lf && crlf >= cr {
+		return LineEnding::CRLF;
+	}
+
+	if cr > lf && cr > crlf {
+		return LineEnding::CR;
+	}
+
+	return LineEnding::LF;
+
+}
+
+/// read the bytes of a file into text with \n line breaks, and the format to write it back with
+pub fn decode(bytes: &[u8]) -> Option<(String, FileFormat)> {
+
+	let (encoding, bom) = detect_encoding(bytes);
+	let body = &bytes[bom..];
+
+	let text = match encoding {
+		Encoding::Utf8 => String::from_utf8(body.to_vec()).ok()?,
+		Encoding::Utf16LE => decode_utf16(body, u16::from_le_bytes)?,
+		Encoding::Utf16BE => decode_utf16(body, u16::from_be_bytes)?,
+		Encoding::Latin1 => body.iter().map(|b| *b as char).collect(),
+	};
+
+	let line_ending = detect_line_ending(&text);
+	let mut text = text.replace("\r\n", "\n");
+
+	if line_ending == LineEnding::CR {
+		text = text.replace('\r', "\n");
+	}
+
+	let final_newline = text.ends_with('\n');
+
+	if final_newline {
+		text.pop();
+	}
+
+	return Some((text, FileFormat {
+		line_ending: line_ending,
+		encoding: encoding,
+		bom: bom > 0,
+		// text typed into an empty file gets one
+		final_newline: final_newline || body.is_empty(),
+		empty: body.is_empty(),
+	}));
+
+}
+
+/// write text with \n line breaks into the bytes of a file, fails with the first char the encoding can't store
+pub fn encode(text: &str, format: &FileFormat) -> Result<Vec<u8>, char> {
+
+	let mut text = text.replace('\n', format.line_ending.as_str());
+
+	if format.final_newline && !(format.empty && text.is_empty()) {
+		text.push_str(format.line_ending.as_str());
+	}
+
+	if let Some(ch) = text.chars().find(|c| !format.encoding.can_encode(*c)) {
+		return Err(ch);
+	}
+
+	let mut bytes = vec![];
+
+	if format.bom {
+		match format.encoding {
+			Encoding::Utf8 => bytes.extend_from_slice(&UTF8_BOM),
+			Encoding::Utf16LE => bytes.extend_from_slice(&UTF16LE_BOM),
+			Encoding::Utf16BE => bytes.extend_from_slice(&UTF16BE_BOM),
+			Encoding::Latin1 => {},
+		}
+	}
+
+	match format.encoding {
+		Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+		Encoding::Utf16LE => text.encode_utf16().for_each(|u| bytes.extend_from_slice(&u.to_le_bytes())),
+		Encoding::Utf16BE => text.encode_utf16().for_each(|u| bytes.extend_from_slice(&u.to_be_bytes())),
+		Encoding::Latin1 => bytes.extend(text.chars().map(|c| c as u8)),
+	}
+
+	return Ok(bytes);
+
+}
+
+impl Buffer {
+
+	/// change how the file is written on the next save, fails if the text can't be stored in a new encoding
+	pub fn set_format(&mut self, opt: FormatOpt) -> Result<(), char> {
+
+		let mut format = self.format;
+
+		match opt {
+			FormatOpt::LineEnding(le) => format.line_ending = le,
+			FormatOpt::Encoding(enc) => format.encoding = enc,
+			FormatOpt::Bom(bom) => format.bom = bom,
+			FormatOpt::FinalNewline(eol) => format.final_newline = eol,
+		}
+
+		if let FormatOpt::Encoding(enc) = opt {
+			if let Some(ch) = self.content.chars().find(|c| !enc.can_encode(*c)) {
+				return Err(ch);
+			}
+		}
+
+		if format != self.format {
+			self.format = format;
+			self.modified = true;
+		}
+
+		return Ok(());
+
+	}
+
+}
+
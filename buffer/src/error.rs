@@ -0,0 +1,122 @@
+// wengwengweng
+
+use std::io;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// what was being done to a path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileOp {
+	Read,
+	Write,
+	/// reading what's in a directory
+	List,
+}
+
+/// why reading, writing or listing a path failed
+#[derive(Debug)]
+pub enum Error {
+	/// there's nothing at the path
+	NotFound(PathBuf, io::Error),
+	/// the path is a directory where a file was wanted
+	IsDirectory(PathBuf, io::Error),
+	/// the path is a file where a directory was wanted
+	NotDirectory(PathBuf),
+	/// the file is read-only, it can still be overwritten by force
+	ReadOnly(PathBuf),
+	/// writing to another path would overwrite a file that's already there
+	Exists(PathBuf),
+	/// not allowed to read or write the file
+	Permission(PathBuf, FileOp, io::Error),
+	/// the file isn't text we can read, or has a char its encoding can't store
+	InvalidEncoding(PathBuf, Option<char>),
+	/// reading failed for another reason
+	Read(PathBuf, io::Error),
+	/// writing failed for another reason
+	Write(PathBuf, io::Error),
+}
+
+impl Error {
+
+	/// sort an io error by its kind
+	pub fn from_io(path: &Path, op: FileOp, err: io::Error) -> Self {
+
+		let path = path.to_path_buf();
+
+		return match err.kind() {
+			io::ErrorKind::NotFound if op != FileOp::Write => Error::NotFound(path, err),
+			io::ErrorKind::PermissionDenied => Error::Permission(path, op, err),
+			_ if op != FileOp::List && path.is_dir() => Error::IsDirectory(path, err),
+			_ => match op {
+				FileOp::Read | FileOp::List => Error::Read(path, err),
+				FileOp::Write => Error::Write(path, err),
+			},
+		};
+
+	}
+
+	/// nothing at a path that was checked before it was opened
+	pub fn not_found(path: &Path) -> Self {
+		return Error::NotFound(path.to_path_buf(), io::Error::from(io::ErrorKind::NotFound));
+	}
+
+	/// a directory found where a file was wanted before it was opened
+	pub fn is_directory(path: &Path) -> Self {
+		return Error::IsDirectory(path.to_path_buf(), io::Error::other("is a directory"));
+	}
+
+	/// get the path that failed
+	pub fn path(&self) -> &Path {
+		return match self {
+			Error::NotFound(path, _) => path,
+			Error::IsDirectory(path, _) => path,
+			Error::NotDirectory(path) => path,
+			Error::ReadOnly(path) => path,
+			Error::Exists(path) => path,
+			Error::Permission(path, _, _) => path,
+			Error::InvalidEncoding(path, _) => path,
+			Error::Read(path, _) => path,
+			Error::Write(path, _) => path,
+		};
+	}
+
+	/// get what went wrong, without the path
+	pub fn reason(&self) -> String {
+		return match self {
+			Error::NotFound(_, _) => String::from("no such file or directory"),
+			Error::IsDirectory(_, _) => String::from("is a directory"),
+			Error::NotDirectory(_) => String::from("not a directory"),
+			Error::ReadOnly(_) => String::from("file is read-only (add ! to override)"),
+			Error::Exists(_) => String::from("file exists (add ! to overwrite)"),
+			Error::Permission(_, FileOp::Read, _) => String::from("no permission to read"),
+			Error::Permission(_, FileOp::Write, _) => String::from("no permission to write"),
+			Error::Permission(_, FileOp::List, _) => String::from("no permission to list"),
+			Error::InvalidEncoding(_, None) => String::from("can't decode as text"),
+			Error::InvalidEncoding(_, Some(ch)) => format!("can't encode {:?}", ch),
+			Error::Read(_, e) => format!("failed to read: {}", e),
+			Error::Write(_, e) => format!("failed to write: {}", e),
+		};
+	}
+
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return write!(f, "{}: {}", self.path().display(), self.reason());
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		return match self {
+			Error::NotFound(_, e) => Some(e),
+			Error::IsDirectory(_, e) => Some(e),
+			Error::Permission(_, _, e) => Some(e),
+			Error::Read(_, e) => Some(e),
+			Error::Write(_, e) => Some(e),
+			_ => None,
+		};
+	}
+}
+
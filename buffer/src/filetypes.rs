@@ -0,0 +1,174 @@
+// wengwengweng
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use super::*;
+
+fn pairs(list: &[(char, char)]) -> HashMap<char, char> {
+	return list.iter().cloned().collect();
+}
+
+fn re(pat: &str) -> Option<Regex> {
+	return Some(Regex::new(pat).unwrap());
+}
+
+fn rust() -> FileType {
+	return FileType {
+		name: String::from("rust"),
+		match_fname: re(r"\.rs$"),
+		match_shebang: re(r"^rust-script$"),
+		comment: Some(String::from("//")),
+		indent_forward: re(r"\{$"),
+		indent_backward: re(r"^\s*\}"),
+		pairs: pairs(&[('(', ')'), ('\'', '\''), ('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/rust.syn")),
+		..FileType::default()
+	};
+}
+
+fn toml() -> FileType {
+	return FileType {
+		name: String::from("toml"),
+		match_fname: re(r"(\.toml|(^|/)Cargo\.lock)$"),
+		comment: Some(String::from("#")),
+		indent_forward: re(r"[\{\[]$"),
+		indent_backward: re(r"^\s*[\}\]]"),
+		pairs: pairs(&[('\'', '\''), ('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/toml.syn")),
+		..FileType::default()
+	};
+}
+
+fn markdown() -> FileType {
+	return FileType {
+		name: String::from("markdown"),
+		match_fname: re(r"\.(md|markdown|mkd)$"),
+		shift_width: 2,
+		expand_tab: true,
+		pairs: pairs(&[('(', ')'), ('[', ']'), ('`', '`')]),
+		syntax: Syntax::new(include_str!("res/markdown.syn")),
+		..FileType::default()
+	};
+}
+
+fn json() -> FileType {
+	return FileType {
+		name: String::from("json"),
+		match_fname: re(r"\.(json|jsonc|json5)$|(^|/)\.(babelrc|eslintrc|prettierrc)$"),
+		shift_width: 2,
+		expand_tab: true,
+		indent_forward: re(r"[\{\[]$"),
+		indent_backward: re(r"^\s*[\}\]]"),
+		pairs: pairs(&[('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/json.syn")),
+		..FileType::default()
+	};
+}
+
+fn c() -> FileType {
+	return FileType {
+		name: String::from("c"),
+		match_fname: re(r"\.[ch]$"),
+		comment: Some(String::from("//")),
+		indent_forward: re(r"[\{\(\[]$"),
+		indent_backward: re(r"^\s*[\}\)\]]"),
+		pairs: pairs(&[('(', ')'), ('\'', '\''), ('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/c.syn")),
+		..FileType::default()
+	};
+}
+
+fn python() -> FileType {
+	return FileType {
+		name: String::from("python"),
+		match_fname: re(r"\.pyw?$"),
+		match_shebang: re(r"^python[0-9.]*$"),
+		comment: Some(String::from("#")),
+		expand_tab: true,
+		indent_forward: re(r"[:\{\(\[]$"),
+		indent_backward: re(r"^\s*[\}\)\]]"),
+		pairs: pairs(&[('(', ')'), ('\'', '\''), ('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/python.syn")),
+		..FileType::default()
+	};
+}
+
+fn javascript() -> FileType {
+	return FileType {
+		name: String::from("javascript"),
+		match_fname: re(r"\.(js|mjs|cjs|jsx)$"),
+		match_shebang: re(r"^(node|nodejs|deno|bun)$"),
+		comment: Some(String::from("//")),
+		shift_width: 2,
+		expand_tab: true,
+		indent_forward: re(r"[\{\(\[]$"),
+		indent_backward: re(r"^\s*[\}\)\]]"),
+		pairs: pairs(&[('(', ')'), ('\'', '\''), ('"', '"'), ('`', '`'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/javascript.syn")),
+		..FileType::default()
+	};
+}
+
+fn sh() -> FileType {
+	return FileType {
+		name: String::from("sh"),
+		match_fname: re(r"\.(sh|bash|zsh)$|(^|/)(\.bashrc|\.bash_profile|\.zshrc|\.profile|PKGBUILD)$"),
+		match_shebang: re(r"^(sh|bash|zsh|dash|ksh)$"),
+		comment: Some(String::from("#")),
+		indent_forward: re(r"(\{|\bthen|\bdo|\bin)$"),
+		indent_backward: re(r"^\s*(\}|fi\b|done\b|esac\b|else\b|elif\b)"),
+		pairs: pairs(&[('(', ')'), ('\'', '\''), ('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/sh.syn")),
+		..FileType::default()
+	};
+}
+
+fn yaml() -> FileType {
+	return FileType {
+		name: String::from("yaml"),
+		match_fname: re(r"\.ya?ml$"),
+		comment: Some(String::from("#")),
+		shift_width: 2,
+		expand_tab: true,
+		indent_forward: re(r":$"),
+		pairs: pairs(&[('\'', '\''), ('"', '"'), ('{', '}'), ('[', ']')]),
+		syntax: Syntax::new(include_str!("res/yaml.syn")),
+		..FileType::default()
+	};
+}
+
+fn text() -> FileType {
+	return FileType {
+		match_fname: re(r"\.txt$"),
+		// anything else that matches wins
+		priority: -1,
+		..FileType::default()
+	};
+}
+
+impl Default for FTRegistry {
+
+	/// the filetypes that come with hop
+	fn default() -> Self {
+
+		let mut registry = Self::new();
+
+		registry.add(text());
+		registry.add(rust());
+		registry.add(toml());
+		registry.add(markdown());
+		registry.add(json());
+		registry.add(c());
+		registry.add(python());
+		registry.add(javascript());
+		registry.add(sh());
+		registry.add(yaml());
+
+		return registry;
+
+	}
+
+}
+
@@ -0,0 +1,402 @@
+// wengwengweng
+
+use std::cmp;
+use std::rc::Rc;
+use std::cell::RefMut;
+use std::collections::HashMap;
+
+use super::*;
+
+/// how fold regions are found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldMethod {
+	/// a line and the lines below it with a deeper indent
+	Indent,
+	/// a line with an open bracket and the lines before its close bracket
+	Brackets,
+}
+
+/// the closed folds, sorted by the first line
+pub(crate) struct ClosedFolds {
+	list: Vec<(Line, Line)>,
+	/// the outermost ones, they don't overlap
+	outer: Vec<(Line, Line)>,
+}
+
+impl ClosedFolds {
+
+	fn new(list: Vec<(Line, Line)>) -> Self {
+
+		let mut outer: Vec<(Line, Line)> = vec![];
+
+		for (start, end) in &list {
+			match outer.last_mut() {
+				Some(last) if *start <= last.1 => last.1 = cmp::max(last.1, *end),
+				_ => outer.push((*start, *end)),
+			}
+		}
+
+		return Self {
+			list: list,
+			outer: outer,
+		};
+
+	}
+
+	/// get the outermost closed fold a line is in, its first line included
+	fn around(&self, ln: Line) -> Option<(Line, Line)> {
+
+		let i = self.outer.partition_point(|(start, _)| *start <= ln);
+		let (start, end) = *self.outer.get(i.checked_sub(1)?)?;
+
+		if ln > end {
+			return None;
+		}
+
+		return Some((start, end));
+
+	}
+
+	/// if a line is hidden in a closed fold
+	pub(crate) fn hides(&self, ln: Line) -> bool {
+		return self.around(ln).map(|(start, _)| ln > start).unwrap_or(false);
+	}
+
+	/// get the first line of the outermost fold hiding a line, or the line if it's visible
+	fn shown(&self, ln: Line) -> Line {
+		return self.around(ln).map(|(start, _)| start).unwrap_or(ln);
+	}
+
+}
+
+/// fold ends and closed folds, kept until the content, the fold method or the closed folds change
+#[derive(Default)]
+pub(crate) struct FoldCache {
+	revision: u64,
+	method: Option<FoldMethod>,
+	ends: HashMap<Line, Option<Line>>,
+	closed: Option<Rc<ClosedFolds>>,
+}
+
+impl Buffer {
+
+	fn fold_cache(&self) -> RefMut<'_, FoldCache> {
+
+		let mut cache = self.fold_cache.borrow_mut();
+
+		if cache.revision != self.revision || cache.method != Some(self.fold_method) {
+			*cache = FoldCache {
+				revision: self.revision,
+				method: Some(self.fold_method),
+				..FoldCache::default()
+			};
+		}
+
+		return cache;
+
+	}
+
+	/// forget the closed folds worked out before, after folds are opened or closed
+	fn folds_changed(&mut self) {
+		self.fold_cache.get_mut().closed = None;
+	}
+
+	/// get the last line of the fold that starts at a line, the start line itself stays visible
+	pub fn fold_end_at(&self, ln: Line) -> Option<Line> {
+
+		let cached = self.fold_cache().ends.get(&ln).cloned();
+
+		if let Some(end) = cached {
+			return end;
+		}
+
+		let end = self.find_fold_end(ln);
+
+		self.fold_cache().ends.insert(ln, end);
+
+		return end;
+
+	}
+
+	fn find_fold_end(&self, ln: Line) -> Option<Line> {
+
+		let last = match self.fold_method {
+			FoldMethod::Indent => self.indent_fold_end(ln)?,
+			FoldMethod::Brackets => self.bracket_fold_end(ln)?,
+		};
+
+		if last <= ln {
+			return None;
+		}
+
+		return Some(last);
+
+	}
+
+	fn indent_fold_end(&self, ln: Line) -> Option<Line> {
+
+		let count = self.line_count() as Line;
+		let level = self.get_indent_at(ln)?;
+		let blank = |ln: Line| self.get_line_at(ln).map(|l| l.trim().is_empty()).unwrap_or(true);
+		let mut last = ln;
+
+		for l in ln + 1..=count {
+			if blank(l) {
+				continue;
+			}
+			if self.get_indent_at(l)? <= level {
+				break;
+			}
+			last = l;
+		}
+
+		return Some(last);
+
+	}
+
+	fn bracket_fold_end(&self, ln: Line) -> Option<Line> {
+
+		let line = self.get_line_at(ln)?;
+
+		for (i, c) in line.char_indices() {
+
+			match self.bracket_of(c) {
+				Some((open, _)) if open == c => {},
+				_ => continue,
+			}
+
+			let pos = Pos::new(ln, byte_to_col(&line, i));
+
+			if let Some((_, close)) = self.matching_pair_at(pos) {
+				if close.line > ln {
+					return Some(close.line - 1);
+				}
+			}
+
+		}
+
+		return None;
+
+	}
+
+	/// get the innermost fold a line is in, as (first line, last line)
+	pub fn fold_around(&self, ln: Line) -> Option<(Line, Line)> {
+
+		for start in (1..=ln).rev() {
+			if let Some(end) = self.fold_end_at(start) {
+				if end >= ln {
+					return Some((start, end));
+				}
+			}
+		}
+
+		return None;
+
+	}
+
+	/// get the closed folds, worked out once until they or the content change
+	pub(crate) fn closed(&self) -> Rc<ClosedFolds> {
+
+		let cached = self.fold_cache().closed.clone();
+
+		if let Some(closed) = cached {
+			return closed;
+		}
+
+		let len = self.content.len_chars();
+		let mut folds: Vec<(Line, Line)> = self.folds
+			.iter()
+			.map(|idx| self.content.char_to_line(cmp::min(*idx, len)) as Line + 1)
+			.filter_map(|ln| self.fold_end_at(ln).map(|end| (ln, end)))
+			.collect();
+
+		folds.sort();
+		folds.dedup();
+
+		let closed = Rc::new(ClosedFolds::new(folds));
+
+		self.fold_cache().closed = Some(closed.clone());
+
+		return closed;
+
+	}
+
+	/// get the closed folds, sorted by the first line
+	pub fn closed_folds(&self) -> Vec<(Line, Line)> {
+		return self.closed().list.clone();
+	}
+
+	/// if a line is hidden in a closed fold
+	pub fn is_hidden(&self, ln: Line) -> bool {
+		return self.closed().hides(ln);
+	}
+
+	/// get the line a line is shown as, the first line of the closed fold it's hidden in
+	pub fn shown_line_at(&self, ln: Line) -> Line {
+		return self.closed().shown(ln);
+	}
+
+	/// close the innermost open fold around a line, returns its first line
+	pub fn close_fold_at(&mut self, ln: Line) -> Option<Line> {
+
+		let closed = self.closed_folds();
+
+		for start in (1..=ln).rev() {
+
+			let end = match self.fold_end_at(start) {
+				Some(end) => end,
+				None => continue,
+			};
+
+			if end >= ln && !closed.contains(&(start, end)) {
+				self.folds.push(self.line_to_char(start)?);
+				self.folds_changed();
+				return Some(start);
+			}
+
+		}
+
+		return None;
+
+	}
+
+	/// open every fold a line is hidden in, and the closed fold starting at it
+	pub fn open_fold_at(&mut self, ln: Line) {
+
+		let open: Vec<Line> = self.closed_folds()
+			.into_iter()
+			.filter(|(start, end)| ln >= *start && ln <= *end)
+			.map(|(start, _)| start)
+			.collect();
+
+		let content = &self.content;
+
+		self.folds.retain(|idx| {
+			let start = content.char_to_line(cmp::min(*idx, content.len_chars())) as Line + 1;
+			return !open.contains(&start);
+		});
+
+		self.folds_changed();
+
+	}
+
+	/// open the fold at a line if it's closed, otherwise close the innermost fold around it
+	pub fn toggle_fold_at(&mut self, ln: Line) -> Option<Line> {
+
+		if let Some(end) = self.fold_end_at(ln) {
+			if self.closed_folds().contains(&(ln, end)) {
+				self.open_fold_at(ln);
+				return Some(ln);
+			}
+		}
+
+		return self.close_fold_at(ln);
+
+	}
+
+	/// close every fold
+	pub fn close_all_folds(&mut self) {
+
+		self.folds = (1..=self.line_count() as Line)
+			.filter(|ln| self.fold_end_at(*ln).is_some())
+			.filter_map(|ln| self.line_to_char(ln))
+			.collect();
+
+		self.folds_changed();
+
+	}
+
+	/// open every fold
+	pub fn open_all_folds(&mut self) {
+		self.folds.clear();
+		self.folds_changed();
+	}
+
+	/// get the line n visible lines below a line, stops at the last line
+	pub fn visible_line_down(&self, ln: Line, n: usize) -> Line {
+
+		let folds = self.closed();
+		let last = self.line_count() as Line;
+		let mut ln = ln;
+
+		for _ in 0..n {
+
+			// skip the fold this line starts
+			let next = match folds.around(ln) {
+				Some((_, end)) => end + 1,
+				None => ln + 1,
+			};
+
+			if next > last {
+				break;
+			}
+
+			ln = next;
+
+		}
+
+		return ln;
+
+	}
+
+	/// get the line n visible lines above a line, stops at the first line
+	pub fn visible_line_up(&self, ln: Line, n: usize) -> Line {
+
+		let folds = self.closed();
+		let mut ln = ln;
+
+		for _ in 0..n {
+
+			if ln <= 1 {
+				break;
+			}
+
+			ln = folds.shown(ln - 1);
+
+		}
+
+		return ln;
+
+	}
+
+	/// get the number of visible lines from a line up to another (exclusive), negative if it's above
+	pub fn visible_rows_between(&self, from: Line, to: Line) -> i32 {
+
+		if to < from {
+			return -self.visible_rows_between(to, from);
+		}
+
+		let folds = self.closed();
+
+		return (from..to)
+			.filter(|ln| !folds.hides(*ln))
+			.count() as i32;
+
+	}
+
+	/// get the visible lines from a line on, at most n of them
+	pub fn visible_lines(&self, from: Line, n: usize) -> Vec<Line> {
+
+		let mut lines = vec![];
+		let mut ln = from;
+
+		while lines.len() < n {
+
+			lines.push(ln);
+
+			let next = self.visible_line_down(ln, 1);
+
+			if next == ln {
+				break;
+			}
+
+			ln = next;
+
+		}
+
+		return lines;
+
+	}
+
+}
+
@@ -1,15 +1,27 @@
 // wengwengweng
 
+use std::rc::Rc;
+use std::cmp;
+use std::cell::RefCell;
+use std::path::Path;
 use std::collections::HashMap;
 
 use regex::Regex;
 
 use super::*;
 
+/// how many lines at the start and end of a file are looked at for a modeline
+const MODELINES: usize = 5;
+
+#[derive(Clone)]
 pub struct FileType {
 
 	pub name: String,
 	pub match_fname: Option<Regex>,
+	/// matched against the interpreter of a #! line, like python3 or bash
+	pub match_shebang: Option<Regex>,
+	/// when several file name patterns match, the highest priority wins
+	pub priority: i32,
 	pub comment: Option<String>,
 	pub shift_width: u32,
 	pub auto_indent: bool,
@@ -26,6 +38,8 @@ impl Default for FileType {
 		return Self {
 			name: String::from("text"),
 			match_fname: None,
+			match_shebang: None,
+			priority: 0,
 			comment: None,
 			shift_width: 4,
 			auto_indent: true,
@@ -38,37 +52,169 @@ impl Default for FileType {
 	}
 }
 
+/// the interpreter a #! line runs, without its dir, looking through env
+pub fn shebang_interpreter(line: &str) -> Option<String> {
+
+	let mut words = line
+		.strip_prefix("#!")?
+		.split_whitespace();
+
+	let mut prog = Path::new(words.next()?).file_name()?.to_str()?;
+
+	if prog == "env" {
+		// skip flags like -S and assignments like A=b
+		prog = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+	}
+
+	return Some(String::from(prog));
+
+}
+
+thread_local! {
+	static MODELINE: Regex = Regex::new(r"(?:^|\s)(?:vi|vim|ex|hop):(?:.*[\s:])?(?:ft|filetype)=([\w+-]+)").unwrap();
+	static SHARED: RefCell<Option<Rc<FTRegistry>>> = const { RefCell::new(None) };
+}
+
+/// the filetype a vim style modeline sets, like "vim: set ft=rust:" or "hop: ft=rust"
+pub fn modeline_filetype(line: &str) -> Option<String> {
+	return MODELINE.with(|re| {
+		let caps = re.captures(line)?;
+		return Some(String::from(&caps[1]));
+	});
+}
+
+/// all the filetypes a buffer can be, one registry is shared by every buffer
 pub struct FTRegistry {
-	list: HashMap<String, FileType>,
+	list: Vec<Rc<FileType>>,
+	/// user definitions that failed to load
+	pub(crate) errors: Vec<FTDefError>,
 }
 
 impl FTRegistry {
 
 	pub fn new() -> Self {
 		return Self {
-			list: HashMap::new(),
+			list: Vec::new(),
+			errors: Vec::new(),
 		};
 	}
 
+	/// the registry buffers use, loaded by the first buffer that's opened
+	pub fn shared() -> Rc<Self> {
+		return SHARED.with(|shared| {
+			return shared
+				.borrow_mut()
+				.get_or_insert_with(|| Rc::new(Self::load()))
+				.clone();
+		});
+	}
+
+	/// get the user definitions that failed to load
+	pub fn errors(&self) -> &[FTDefError] {
+		return &self.errors;
+	}
+
+	/// add a filetype, replacing the one with the same name
 	pub fn add(&mut self, ft: FileType) {
-		self.list.insert(ft.name.clone(), ft);
+
+		let ft = Rc::new(ft);
+
+		match self.list.iter_mut().find(|t| t.name == ft.name) {
+			Some(t) => *t = ft,
+			None => self.list.push(ft),
+		}
+
+	}
+
+	pub fn get(&self, name: &str) -> Option<Rc<FileType>> {
+		return self.list
+			.iter()
+			.find(|ft| ft.name == name)
+			.cloned();
 	}
 
-	pub fn get(&self, name: &str) -> Option<&FileType> {
-		return self.list.get(name);
+	/// get the names of all filetypes, in the order they were added
+	pub fn names(&self) -> Vec<&str> {
+		return self.list
+			.iter()
+			.map(|ft| ft.name.as_str())
+			.collect();
 	}
 
-	pub fn find_for(&self, fname: &str) -> Option<&FileType> {
+	/// find a filetype by file name, the highest priority wins and on a tie the one added last
+	pub fn find_for(&self, fname: &str) -> Option<Rc<FileType>> {
 
-		for ft in self.list.values() {
+		let mut found: Option<&Rc<FileType>> = None;
+
+		for ft in &self.list {
 			if let Some(match_fname) = &ft.match_fname {
-				if match_fname.is_match(fname) {
-					return Some(ft);
+				if match_fname.is_match(fname) && found.map(|f| ft.priority >= f.priority).unwrap_or(true) {
+					found = Some(ft);
 				}
 			}
 		}
 
-		return None;
+		return found.cloned();
+
+	}
+
+	/// find a filetype by the #! line a file starts with
+	pub fn find_for_shebang(&self, line: &str) -> Option<Rc<FileType>> {
+
+		let prog = shebang_interpreter(line)?;
+
+		return self.list
+			.iter()
+			.rev()
+			.find(|ft| ft.match_shebang.as_ref().map(|re| re.is_match(&prog)).unwrap_or(false))
+			.cloned();
+
+	}
+
+	/// find a filetype by a modeline
+	pub fn find_for_modeline(&self, line: &str) -> Option<Rc<FileType>> {
+		return self.get(&modeline_filetype(line)?);
+	}
+
+}
+
+impl Buffer {
+
+	/// pick the filetype from a modeline in the first or last lines, the #! line, or the file name, in that order
+	pub fn detect_filetype(&mut self) {
+
+		let count = self.content.len_lines();
+		let head = cmp::min(MODELINES, count);
+		let tail = cmp::max(head, count.saturating_sub(MODELINES));
+		let lines: Vec<String> = (0..head)
+			.chain(tail..count)
+			.map(|i| self.content.line(i).to_string())
+			.collect();
+
+		let fname = format!("{}", self.path.display());
+
+		let ft = lines
+			.iter()
+			.find_map(|l| self.filetypes.find_for_modeline(l))
+			.or_else(|| lines.first().and_then(|l| self.filetypes.find_for_shebang(l.trim_end())))
+			.or_else(|| self.filetypes.find_for(&fname))
+			.or_else(|| self.filetypes.get("text"))
+			.unwrap_or_else(|| Rc::new(FileType::default()));
+
+		self.filetype = ft;
+
+	}
+
+	/// switch to a filetype by name, false if there's no such filetype
+	pub fn set_filetype(&mut self, name: &str) -> bool {
+
+		return match self.filetypes.get(name) {
+			Some(ft) => {
+				self.filetype = ft;
+				true
+			},
+			None => false,
+		};
 
 	}
 
@@ -0,0 +1,255 @@
+// wengwengweng
+
+use std::fs;
+use std::io;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use super::*;
+
+/// a filetype as written in a toml file, fields that aren't set are kept from the filetype with the same name
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileTypeDef {
+	name: String,
+	/// regexes matched against the file path
+	filenames: Option<Vec<String>>,
+	/// regexes matched against the interpreter of a #! line
+	shebangs: Option<Vec<String>>,
+	priority: Option<i32>,
+	/// the token that starts a line comment, block comments aren't supported
+	comment: Option<String>,
+	/// can't be 0
+	shift_width: Option<u32>,
+	auto_indent: Option<bool>,
+	expand_tab: Option<bool>,
+	indent_forward: Option<String>,
+	indent_backward: Option<String>,
+	/// open and close chars, like "()"
+	pairs: Option<Vec<String>>,
+	/// a .syn grammar, relative to the toml file
+	syntax: Option<PathBuf>,
+}
+
+/// why a filetype definition couldn't be loaded
+#[derive(Clone, Debug)]
+pub struct FTDefError {
+	pub path: PathBuf,
+	pub line: Option<usize>,
+	pub msg: String,
+}
+
+impl fmt::Display for FTDefError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return match self.line {
+			Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.msg),
+			None => write!(f, "{}: {}", self.path.display(), self.msg),
+		};
+	}
+}
+
+impl std::error::Error for FTDefError {}
+
+/// where the user's filetype definitions are kept
+pub fn filetypes_dir() -> Option<PathBuf> {
+	return Some(dirs::config_dir()?.join("hop").join("filetypes"));
+}
+
+/// the line a byte offset is on, starting from 1
+fn line_at(src: &str, offset: usize) -> usize {
+	return src[..offset].matches('\n').count() + 1;
+}
+
+/// the line a key is set on, to point errors found after parsing at it
+fn line_of_key(src: &str, key: &str) -> Option<usize> {
+
+	let line = src.lines().position(|l| {
+		let l = l.trim_start();
+		l.starts_with(key) && l[key.len()..].trim_start().starts_with('=')
+	})?;
+
+	return Some(line + 1);
+
+}
+
+/// one regex out of a list of them, none for an empty list
+fn patterns(list: &[String]) -> Result<Option<Regex>, regex::Error> {
+
+	if list.is_empty() {
+		return Ok(None);
+	}
+
+	let pat = list
+		.iter()
+		.map(|p| format!("(?:{})", p))
+		.collect::<Vec<String>>()
+		.join("|");
+
+	return Regex::new(&pat).map(Some);
+
+}
+
+/// a regex, none for an empty string
+fn pattern(pat: &str) -> Result<Option<Regex>, regex::Error> {
+
+	if pat.is_empty() {
+		return Ok(None);
+	}
+
+	return Regex::new(pat).map(Some);
+
+}
+
+impl FTRegistry {
+
+	/// load a filetype definition from a toml file, merged over the filetype with the same name
+	pub fn load_def(&mut self, path: &Path) -> Result<(), FTDefError> {
+
+		let err = |line: Option<usize>, msg: String| FTDefError {
+			path: path.to_path_buf(),
+			line: line,
+			msg: msg,
+		};
+
+		let src = fs::read_to_string(path)
+			.map_err(|e| err(None, format!("{}", e)))?;
+
+		let def: FileTypeDef = toml::from_str(&src)
+			.map_err(|e| err(e.span().map(|s| line_at(&src, s.start)), String::from(e.message())))?;
+
+		let key_err = |key: &str, msg: String| err(line_of_key(&src, key), msg);
+
+		let mut ft = match self.get(&def.name) {
+			Some(ft) => (*ft).clone(),
+			None => FileType {
+				name: def.name.clone(),
+				..FileType::default()
+			},
+		};
+
+		if let Some(list) = &def.filenames {
+			ft.match_fname = patterns(list)
+				.map_err(|e| key_err("filenames", format!("invalid pattern: {}", e)))?;
+		}
+
+		if let Some(list) = &def.shebangs {
+			ft.match_shebang = patterns(list)
+				.map_err(|e| key_err("shebangs", format!("invalid pattern: {}", e)))?;
+		}
+
+		if let Some(pat) = &def.indent_forward {
+			ft.indent_forward = pattern(pat)
+				.map_err(|e| key_err("indent_forward", format!("invalid pattern: {}", e)))?;
+		}
+
+		if let Some(pat) = &def.indent_backward {
+			ft.indent_backward = pattern(pat)
+				.map_err(|e| key_err("indent_backward", format!("invalid pattern: {}", e)))?;
+		}
+
+		if let Some(comment) = &def.comment {
+			ft.comment = Some(comment.clone()).filter(|c| !c.is_empty());
+		}
+
+		if let Some(list) = &def.pairs {
+
+			let mut pairs = HashMap::new();
+
+			for p in list {
+
+				let chars: Vec<char> = p.chars().collect();
+
+				if chars.len() != 2 {
+					return Err(key_err("pairs", format!("a pair should be 2 chars: {:?}", p)));
+				}
+
+				pairs.insert(chars[0], chars[1]);
+
+			}
+
+			ft.pairs = pairs;
+
+		}
+
+		if let Some(file) = &def.syntax {
+
+			let file = match path.parent() {
+				Some(dir) => dir.join(file),
+				None => file.clone(),
+			};
+
+			let code = fs::read_to_string(&file)
+				.map_err(|e| key_err("syntax", format!("{}: {}", file.display(), e)))?;
+
+			ft.syntax = Syntax::compile(&code)
+				.map_err(|e| FTDefError {
+					path: file.clone(),
+					line: Some(e.line),
+					msg: e.msg,
+				})?;
+
+		}
+
+		if def.shift_width == Some(0) {
+			return Err(key_err("shift_width", String::from("shift width can't be 0")));
+		}
+
+		ft.priority = def.priority.unwrap_or(ft.priority);
+		ft.shift_width = def.shift_width.unwrap_or(ft.shift_width);
+		ft.auto_indent = def.auto_indent.unwrap_or(ft.auto_indent);
+		ft.expand_tab = def.expand_tab.unwrap_or(ft.expand_tab);
+
+		self.add(ft);
+
+		return Ok(());
+
+	}
+
+	/// load every .toml file in a dir in the order of their names, returns the ones that failed
+	pub fn load_dir(&mut self, dir: &Path) -> Vec<FTDefError> {
+
+		let entries = match fs::read_dir(dir) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return vec![],
+			Err(e) => return vec![FTDefError {
+				path: dir.to_path_buf(),
+				line: None,
+				msg: format!("{}", e),
+			}],
+		};
+
+		let mut files: Vec<PathBuf> = entries
+			.filter_map(|e| e.ok())
+			.map(|e| e.path())
+			.filter(|p| p.extension().map(|ext| ext == "toml").unwrap_or(false))
+			.collect();
+
+		files.sort();
+
+		return files
+			.iter()
+			.filter_map(|f| self.load_def(f).err())
+			.collect();
+
+	}
+
+	/// the built-in filetypes with the ones in the user's config dir merged over them, see errors() for the ones that failed
+	pub fn load() -> Self {
+
+		let mut registry = Self::default();
+
+		if let Some(dir) = filetypes_dir() {
+			registry.errors = registry.load_dir(&dir);
+		}
+
+		return registry;
+
+	}
+
+}
+
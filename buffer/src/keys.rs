@@ -0,0 +1,1097 @@
+// wengwengweng
+
+use std::cmp;
+
+use super::*;
+
+/// an input key, independent of any window system
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+	Char(char),
+	Ctrl(char),
+	Enter,
+	Escape,
+	Backspace,
+	Tab,
+	Up,
+	Down,
+	Left,
+	Right,
+}
+
+/// an action over the text a motion moves across
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+	Delete,
+	Change,
+	Yank,
+	IndentForward,
+	IndentBackward,
+	Comment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Motion {
+	Left,
+	Right,
+	Up,
+	Down,
+	NextWord,
+	WordEnd,
+	PrevWord,
+	LineStart,
+	FirstNonBlank,
+	LineEnd,
+	/// gg, or the line of the count
+	FirstLine,
+	/// G, or the line of the count
+	LastLine,
+	FindNext(char),
+	FindPrev(char),
+	TillNext(char),
+	TillPrev(char),
+	SearchNext,
+	SearchPrev,
+	/// % the bracket matching the first one at or after the cursor
+	MatchPair,
+	/// 'a the line of a mark
+	Mark(char),
+	/// `a the position of a mark
+	MarkExact(char),
+	/// the current line and count - 1 lines below, for doubled operators like dd
+	Line,
+}
+
+impl Motion {
+
+	/// if an operator over this motion works on whole lines
+	pub fn is_linewise(&self) -> bool {
+		return matches!(self, Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine | Motion::Mark(_) | Motion::Line);
+	}
+
+	/// if moving this way is remembered in the jump list
+	pub fn is_jump(&self) -> bool {
+		return matches!(self, Motion::FirstLine | Motion::LastLine | Motion::SearchNext | Motion::SearchPrev | Motion::MatchPair | Motion::Mark(_) | Motion::MarkExact(_));
+	}
+
+	/// if an operator over this motion includes the char it lands on
+	pub fn is_inclusive(&self) -> bool {
+		return matches!(self, Motion::LineEnd | Motion::WordEnd | Motion::MatchPair | Motion::FindNext(_) | Motion::FindPrev(_) | Motion::TillNext(_) | Motion::TillPrev(_));
+	}
+
+	/// if the count is a line number instead of a repeat count
+	fn takes_line(&self) -> bool {
+		return matches!(self, Motion::FirstLine | Motion::LastLine | Motion::Line);
+	}
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+	Move(Motion),
+	Operate(Operator, Motion),
+	/// an operator on a text object, inner or around
+	OperateObject(Operator, TextObject, bool),
+	/// an operator on the current selection
+	OperateSelection(Operator),
+	/// select a text object, inner or around
+	SelectObject(TextObject, bool),
+	/// start recording keys into a register
+	Record(char),
+	/// feed the keys in a register
+	Replay(char),
+	SetMark(char),
+	JumpBack,
+	JumpForward,
+	FoldToggle,
+	FoldOpen,
+	FoldClose,
+	FoldOpenAll,
+	FoldCloseAll,
+	/// go back in undo history across branches, g-
+	Earlier,
+	/// go forward in undo history across branches, g+
+	Later,
+	/// switch to the previous or next sibling undo branch, g[ and g]
+	PrevBranch,
+	NextBranch,
+	Insert,
+	Append,
+	InsertLineStart,
+	AppendLineEnd,
+	OpenBelow,
+	OpenAbove,
+	DelChar,
+	Paste,
+	Undo,
+	Redo,
+	Select(SelectKind),
+	Command,
+	Search,
+	AddCursorAbove,
+	AddCursorBelow,
+	AddCursorNextWord,
+	Reset,
+}
+
+/// the result of parsing a key sequence
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parse {
+	/// more keys are needed
+	Incomplete,
+	/// the keys don't make a command
+	Invalid,
+	Complete {
+		action: Action,
+		count: Option<usize>,
+		/// the register picked with a " prefix, like "a
+		register: Option<char>,
+	},
+}
+
+/// what happened to a key fed to a buffer
+#[derive(Debug)]
+pub enum Feed {
+	/// waiting for more keys
+	Pending,
+	Done,
+	/// the keys didn't make a command and were dropped
+	Invalid,
+	/// the command ran but the motion couldn't move
+	Failed,
+	/// a command line was run
+	Command(Result<CmdAction, CmdError>),
+}
+
+enum MotionParse {
+	Done(Motion, usize),
+	Incomplete,
+	None,
+}
+
+enum ObjectParse {
+	Done(TextObject, bool),
+	Incomplete,
+	None,
+}
+
+fn read_count(keys: &[Key], i: &mut usize) -> Option<usize> {
+
+	let mut count = None;
+
+	while let Some(Key::Char(ch)) = keys.get(*i) {
+
+		let digit = match ch.to_digit(10) {
+			Some(0) if count.is_none() => break,
+			Some(d) => d as usize,
+			None => break,
+		};
+
+		count = Some(count.unwrap_or(0) * 10 + digit);
+		*i += 1;
+
+	}
+
+	return count;
+
+}
+
+fn parse_motion(keys: &[Key]) -> MotionParse {
+
+	let ch = match keys.first() {
+		Some(Key::Char(ch)) => *ch,
+		Some(Key::Left) => 'h',
+		Some(Key::Right) => 'l',
+		Some(Key::Down) => 'j',
+		Some(Key::Up) => 'k',
+		Some(_) => return MotionParse::None,
+		None => return MotionParse::Incomplete,
+	};
+
+	let with_char = |f: fn(char) -> Motion| {
+		return match keys.get(1) {
+			Some(Key::Char(target)) => MotionParse::Done(f(*target), 2),
+			Some(_) => MotionParse::None,
+			None => MotionParse::Incomplete,
+		};
+	};
+
+	let motion = match ch {
+		'h' => Motion::Left,
+		'l' => Motion::Right,
+		'j' => Motion::Down,
+		'k' => Motion::Up,
+		'w' => Motion::NextWord,
+		'e' | 'L' => Motion::WordEnd,
+		'b' | 'H' => Motion::PrevWord,
+		'0' => Motion::LineStart,
+		'^' => Motion::FirstNonBlank,
+		'$' => Motion::LineEnd,
+		'G' => Motion::LastLine,
+		'%' => Motion::MatchPair,
+		'n' => Motion::SearchNext,
+		'N' => Motion::SearchPrev,
+		'f' => return with_char(Motion::FindNext),
+		'F' => return with_char(Motion::FindPrev),
+		't' => return with_char(Motion::TillNext),
+		'T' => return with_char(Motion::TillPrev),
+		'\'' => return with_char(Motion::Mark),
+		'`' => return with_char(Motion::MarkExact),
+		'g' => {
+			return match keys.get(1) {
+				Some(Key::Char('g')) => MotionParse::Done(Motion::FirstLine, 2),
+				Some(_) => MotionParse::None,
+				None => MotionParse::Incomplete,
+			};
+		},
+		_ => return MotionParse::None,
+	};
+
+	return MotionParse::Done(motion, 1);
+
+}
+
+/// parse a text object like iw or a(
+fn parse_object(keys: &[Key]) -> ObjectParse {
+
+	let inner = match keys.first() {
+		Some(Key::Char('i')) => true,
+		Some(Key::Char('a')) => false,
+		Some(_) => return ObjectParse::None,
+		None => return ObjectParse::Incomplete,
+	};
+
+	return match keys.get(1) {
+		Some(Key::Char(ch)) => ObjectParse::Done(TextObject::from_char(*ch), inner),
+		Some(_) => ObjectParse::None,
+		None => ObjectParse::Incomplete,
+	};
+
+}
+
+fn operator_of(key: Key) -> Option<Operator> {
+	return match key {
+		Key::Char('d') => Some(Operator::Delete),
+		Key::Char('c') => Some(Operator::Change),
+		Key::Char('y') => Some(Operator::Yank),
+		Key::Char('>') => Some(Operator::IndentForward),
+		Key::Char('<') => Some(Operator::IndentBackward),
+		Key::Char('/') => Some(Operator::Comment),
+		_ => None,
+	};
+}
+
+fn action_of(key: Key, select: bool) -> Option<Action> {
+
+	if select {
+		return match key {
+			Key::Char('x') => Some(Action::OperateSelection(Operator::Delete)),
+			Key::Char('v') => Some(Action::Select(SelectKind::Char)),
+			Key::Char('V') => Some(Action::Select(SelectKind::Line)),
+			Key::Char('B') | Key::Ctrl('v') => Some(Action::Select(SelectKind::Block)),
+			Key::Char(':') => Some(Action::Command),
+			Key::Escape => Some(Action::Reset),
+			_ => None,
+		};
+	}
+
+	return match key {
+		Key::Char('i') => Some(Action::Insert),
+		Key::Char('a') | Key::Enter => Some(Action::Append),
+		Key::Char('I') => Some(Action::InsertLineStart),
+		Key::Char('A') => Some(Action::AppendLineEnd),
+		Key::Char('o') => Some(Action::OpenBelow),
+		Key::Char('O') => Some(Action::OpenAbove),
+		Key::Char('x') => Some(Action::DelChar),
+		Key::Char('p') => Some(Action::Paste),
+		Key::Char('u') => Some(Action::Undo),
+		Key::Char('U') | Key::Ctrl('r') => Some(Action::Redo),
+		Key::Ctrl('o') => Some(Action::JumpBack),
+		Key::Ctrl('i') | Key::Tab => Some(Action::JumpForward),
+		Key::Char('v') => Some(Action::Select(SelectKind::Char)),
+		Key::Char('V') => Some(Action::Select(SelectKind::Line)),
+		Key::Char('B') | Key::Ctrl('v') => Some(Action::Select(SelectKind::Block)),
+		Key::Char(':') => Some(Action::Command),
+		Key::Char('?') => Some(Action::Search),
+		Key::Char('J') => Some(Action::AddCursorBelow),
+		Key::Char('K') => Some(Action::AddCursorAbove),
+		Key::Char('+') => Some(Action::AddCursorNextWord),
+		Key::Escape => Some(Action::Reset),
+		_ => None,
+	};
+
+}
+
+/// parse a normal mode key sequence like "d3w", "5j" or "yy"
+pub fn parse_keys(keys: &[Key], select: bool) -> Parse {
+
+	let mut i = 0;
+
+	let register = match keys.first() {
+		Some(Key::Char('"')) => match keys.get(1) {
+			Some(Key::Char(ch)) => {
+				i = 2;
+				Some(*ch)
+			},
+			Some(_) => return Parse::Invalid,
+			None => return Parse::Incomplete,
+		},
+		_ => None,
+	};
+
+	let count = read_count(keys, &mut i);
+	let key = match keys.get(i) {
+		Some(key) => *key,
+		None => return Parse::Incomplete,
+	};
+
+	let complete = |action: Action, count: Option<usize>, used: usize| {
+		if used == keys.len() {
+			return Parse::Complete {
+				action: action,
+				count: count,
+				register: register,
+			};
+		}
+		return Parse::Invalid;
+	};
+
+	let with_char: Option<fn(char) -> Action> = match key {
+		Key::Char('q') => Some(Action::Record),
+		Key::Char('@') => Some(Action::Replay),
+		Key::Char('m') => Some(Action::SetMark),
+		_ => None,
+	};
+
+	if key == Key::Char('z') {
+		let action = match keys.get(i + 1) {
+			Some(Key::Char('a')) => Action::FoldToggle,
+			Some(Key::Char('o')) => Action::FoldOpen,
+			Some(Key::Char('c')) => Action::FoldClose,
+			Some(Key::Char('R')) => Action::FoldOpenAll,
+			Some(Key::Char('M')) => Action::FoldCloseAll,
+			Some(_) => return Parse::Invalid,
+			None => return Parse::Incomplete,
+		};
+		return complete(action, count, i + 2);
+	}
+
+	if key == Key::Char('g') && !select {
+		match keys.get(i + 1) {
+			Some(Key::Char('-')) => return complete(Action::Earlier, count, i + 2),
+			Some(Key::Char('+')) => return complete(Action::Later, count, i + 2),
+			Some(Key::Char('[')) => return complete(Action::PrevBranch, count, i + 2),
+			Some(Key::Char(']')) => return complete(Action::NextBranch, count, i + 2),
+			_ => {},
+		}
+	}
+
+	if let Some(f) = with_char {
+		return match keys.get(i + 1) {
+			Some(Key::Char(ch)) => complete(f(*ch), count, i + 2),
+			Some(_) => Parse::Invalid,
+			None => Parse::Incomplete,
+		};
+	}
+
+	if let Some(op) = operator_of(key) {
+
+		if select {
+			return complete(Action::OperateSelection(op), count, i + 1);
+		}
+
+		i += 1;
+
+		let count2 = read_count(keys, &mut i);
+		let count = match (count, count2) {
+			(Some(a), Some(b)) => Some(a * b),
+			(a, b) => a.or(b),
+		};
+
+		match keys.get(i) {
+			Some(k) if *k == key => return complete(Action::Operate(op, Motion::Line), count, i + 1),
+			_ => {},
+		}
+
+		match parse_object(&keys[i..]) {
+			ObjectParse::Done(obj, inner) => return complete(Action::OperateObject(op, obj, inner), count, i + 2),
+			ObjectParse::Incomplete => return Parse::Incomplete,
+			ObjectParse::None => {},
+		}
+
+		return match parse_motion(&keys[i..]) {
+			MotionParse::Done(motion, used) => complete(Action::Operate(op, motion), count, i + used),
+			MotionParse::Incomplete => Parse::Incomplete,
+			MotionParse::None => Parse::Invalid,
+		};
+
+	}
+
+	if select {
+		match parse_object(&keys[i..]) {
+			ObjectParse::Done(obj, inner) => return complete(Action::SelectObject(obj, inner), count, i + 2),
+			ObjectParse::Incomplete => return Parse::Incomplete,
+			ObjectParse::None => {},
+		}
+	}
+
+	return match parse_motion(&keys[i..]) {
+		MotionParse::Done(motion, used) => complete(Action::Move(motion), count, i + used),
+		MotionParse::Incomplete => Parse::Incomplete,
+		MotionParse::None => match action_of(key, select) {
+			Some(action) => complete(action, count, i + 1),
+			None => Parse::Invalid,
+		},
+	};
+
+}
+
+impl Buffer {
+
+	/// feed a key, keys are collected until they make a command in normal and select mode
+	pub fn feed(&mut self, key: Key) -> Feed {
+
+		if self.record_key(key) {
+			return Feed::Done;
+		}
+
+		match &self.mode {
+
+			Mode::Insert => {
+				match key {
+					Key::Char(ch) => self.insert(ch),
+					Key::Tab => self.insert('\t'),
+					Key::Enter => self.break_line(),
+					Key::Backspace => self.del(),
+					Key::Ctrl('w') => self.del_word(),
+					Key::Left => self.move_left(),
+					Key::Right => self.move_right(),
+					Key::Up => self.move_up(),
+					Key::Down => self.move_down(),
+					Key::Escape => self.start_normal(),
+					_ => return Feed::Invalid,
+				}
+			},
+
+			Mode::Command { .. } => {
+				match key {
+					Key::Char(ch) => self.command_input(ch),
+					Key::Backspace => self.command_del(),
+					Key::Enter => return Feed::Command(self.command_confirm()),
+					Key::Escape => self.command_cancel(),
+					_ => return Feed::Invalid,
+				}
+			},
+
+			Mode::Search { .. } => {
+				match key {
+					Key::Char(ch) => self.search_input(ch),
+					Key::Backspace => self.search_del(),
+					Key::Up => self.search_history_prev(),
+					Key::Down => self.search_history_next(),
+					Key::Enter => self.search_confirm(),
+					Key::Escape => self.search_cancel(),
+					_ => return Feed::Invalid,
+				}
+			},
+
+			Mode::Normal | Mode::Select { .. } => {
+
+				let select = self.select_kind().is_some();
+
+				self.pending_keys.push(key);
+
+				return match parse_keys(&self.pending_keys, select) {
+					Parse::Incomplete => Feed::Pending,
+					Parse::Invalid => {
+						self.pending_keys.clear();
+						Feed::Invalid
+					},
+					Parse::Complete { action, count, register } => {
+						self.pending_keys.clear();
+						self.register = register;
+						let feed = self.run_action(action, count);
+						self.register = None;
+						// a jump into a closed fold opens it
+						if self.is_hidden(self.cursor.line) {
+							self.open_fold_at(self.cursor.line);
+						}
+						feed
+					},
+				};
+
+			},
+
+		}
+
+		return Feed::Done;
+
+	}
+
+	/// get the keys waiting to make a command
+	pub fn pending_keys(&self) -> &[Key] {
+		return &self.pending_keys;
+	}
+
+	/// get where a motion goes from a position, count is the line for motions that go to a line
+	pub fn motion_target(&self, pos: Pos, motion: Motion, count: Option<usize>) -> Option<Pos> {
+
+		let last = self.line_count() as Line;
+		let goto_line = |ln: usize| {
+			return self.line_start_at(Pos::new(clamp(ln as Line, 1, last), 1));
+		};
+		let find = |s: &str, forward: bool| {
+			if forward {
+				return self.search_next_inline_at(pos, s);
+			} else if pos.col > 1 {
+				return self.search_prev_inline_at(Pos {
+					col: pos.col - 1,
+					.. pos
+				}, s);
+			}
+			return None;
+		};
+
+		let target = match motion {
+
+			Motion::Left if pos.col > 1 => Some(Pos {
+				col: pos.col - 1,
+				.. pos
+			}),
+
+			Motion::Right if pos.col < self.line_len(pos.line) => Some(Pos {
+				col: pos.col + 1,
+				.. pos
+			}),
+
+			Motion::Up if pos.line > 1 => Some(Pos {
+				line: self.visible_line_up(pos.line, 1),
+				.. pos
+			}),
+
+			Motion::Down if pos.line < last => Some(Pos {
+				line: self.visible_line_down(pos.line, 1),
+				.. pos
+			}),
+
+			Motion::Left | Motion::Right | Motion::Up | Motion::Down => None,
+			Motion::NextWord => self.next_word_start_at(pos),
+			Motion::WordEnd => self.next_word_at(pos),
+			Motion::PrevWord => self.prev_word_at(pos),
+			Motion::LineStart => Some(Pos {
+				col: 1,
+				.. pos
+			}),
+			Motion::FirstNonBlank => Some(self.line_start_at(pos)),
+			Motion::LineEnd => Some(self.line_end_at(pos)),
+			Motion::FirstLine => Some(goto_line(count.unwrap_or(1))),
+			Motion::LastLine => Some(goto_line(count.unwrap_or(last as usize))),
+			Motion::Line => Some(Pos::new(clamp(pos.line + count.unwrap_or(1) as Line - 1, 1, last), 1)),
+			Motion::FindNext(ch) => find(&ch.to_string(), true),
+			Motion::FindPrev(ch) => find(&ch.to_string(), false),
+			Motion::TillNext(ch) => find(&ch.to_string(), true).map(|p| Pos {
+				col: p.col - 1,
+				.. p
+			}),
+			Motion::TillPrev(ch) => find(&ch.to_string(), false).map(|p| Pos {
+				col: p.col + 1,
+				.. p
+			}),
+			Motion::SearchNext => self.last_search.as_ref().and_then(|q| self.find_next_at(q, pos)).map(|r| r.start),
+			Motion::SearchPrev => self.last_search.as_ref().and_then(|q| self.find_prev_at(q, pos)).map(|r| r.start),
+			Motion::MatchPair => self.find_match_at(pos),
+			Motion::Mark(ch) => self.mark(ch).map(|p| self.line_start_at(p)),
+			Motion::MarkExact(ch) => self.mark(ch),
+
+		};
+
+		return target.filter(|p| *p != pos);
+
+	}
+
+	/// apply a motion count times, fails if it can't move at all
+	fn motion_repeat(&self, pos: Pos, motion: Motion, count: Option<usize>) -> Option<Pos> {
+
+		if motion.takes_line() {
+			return self.motion_target(pos, motion, count);
+		}
+
+		let mut cur = self.motion_target(pos, motion, None)?;
+
+		for _ in 1..count.unwrap_or(1) {
+			match self.motion_target(cur, motion, None) {
+				Some(next) => cur = next,
+				None => break,
+			}
+		}
+
+		return Some(cur);
+
+	}
+
+	/// get the range an operator works on, and if it's whole lines
+	pub fn motion_range(&self, pos: Pos, motion: Motion, count: Option<usize>) -> Option<(Range, bool)> {
+
+		let mut target = if motion == Motion::Line {
+			self.motion_target(pos, motion, count).unwrap_or(pos)
+		} else {
+			self.motion_repeat(pos, motion, count)?
+		};
+
+		// a word motion stops at the end of the line it started on
+		if motion == Motion::NextWord && target.line > pos.line {
+			target = Pos::new(pos.line, self.line_len(pos.line) + 1);
+		}
+
+		let start = cmp::min(pos, target);
+		let end = cmp::max(pos, target);
+
+		if motion.is_linewise() {
+			return Some((Range {
+				start: Pos::new(start.line, 1),
+				end: Pos::new(end.line, self.line_len(end.line)),
+			}, true));
+		}
+
+		if motion.is_inclusive() {
+			return Some((Range {
+				start: start,
+				end: end,
+			}, false));
+		}
+
+		return Some((Range {
+			start: start,
+			end: Pos {
+				col: end.col - 1,
+				.. end
+			},
+		}, false));
+
+	}
+
+	/// enter insert mode with the cursor at a position, which can be right after the line end
+	fn insert_at_pos(&mut self, pos: Pos) {
+		self.child_cursors.clear();
+		self.mode = Mode::Insert;
+		self.move_to(pos);
+	}
+
+	/// run an operator over a range
+	pub fn operate(&mut self, op: Operator, r: Range, linewise: bool) {
+
+		let (start, end) = (r.start.line, r.end.line);
+
+		match op {
+
+			Operator::Delete if linewise => {
+
+				self.yank_operated(r, linewise);
+				self.push_undo();
+				self.begin_undo_group();
+
+				for ln in (start..=end).rev() {
+					self.del_line_at(ln);
+				}
+
+				self.end_undo_group();
+				self.move_to(self.line_start_at(Pos::new(cmp::min(start, self.line_count() as Line), 1)));
+
+			},
+
+			Operator::Delete => {
+				self.yank_operated(r, linewise);
+				let pos = self.del_range(r);
+				self.move_to(pos);
+			},
+
+			Operator::Change => {
+				self.yank_operated(r, linewise);
+				let pos = self.replace_range(r, "");
+				self.insert_at_pos(pos);
+			},
+
+			Operator::Yank => {
+				self.yank_operated(r, linewise);
+				self.move_to(r.start);
+			},
+
+			Operator::IndentForward => self.edit_lines(start, end, |buf, ln| buf.indent_forward_at(ln)),
+			Operator::IndentBackward => self.edit_lines(start, end, |buf, ln| buf.indent_backward_at(ln)),
+			Operator::Comment => self.toggle_comment_lines(start, end),
+
+		}
+
+	}
+
+	/// copy the text an operator works on to the register in use
+	fn yank_operated(&mut self, r: Range, linewise: bool) {
+
+		if linewise {
+			let text = self.lines_text(r.start.line, r.end.line);
+			self.yank_text(&text, RegisterKind::Line);
+		} else if let Some(text) = self.get_range(r) {
+			self.yank_text(&text, RegisterKind::Char);
+		}
+
+	}
+
+	/// run an operator on the current selection
+	fn operate_selection(&mut self, op: Operator) {
+
+		match op {
+
+			Operator::Delete => self.select_delete(),
+			Operator::Yank => self.select_yank(),
+			Operator::IndentForward => self.select_indent_forward(),
+			Operator::IndentBackward => self.select_indent_backward(),
+			Operator::Comment => self.select_toggle_comment(),
+
+			Operator::Change => {
+
+				let ranges = self.selections();
+
+				if let (Some(first), Some(last)) = (ranges.first(), ranges.last()) {
+
+					let r = Range {
+						start: first.start,
+						end: last.end,
+					};
+
+					if self.select_kind() == Some(SelectKind::Block) {
+						self.select_replace("");
+					} else {
+						self.replace_range(r, "");
+						self.end_select();
+					}
+
+					self.insert_at_pos(first.start);
+
+				}
+
+			},
+
+		}
+
+	}
+
+	/// run a parsed command
+	pub fn run_action(&mut self, action: Action, count: Option<usize>) -> Feed {
+
+		let n = count.unwrap_or(1);
+
+		match action {
+
+			Action::Move(motion) => {
+
+				let before = self.cursor;
+
+				self.move_each(|buf, pos| {
+					// up and down go by rows on screen, operators still take whole lines
+					return match motion {
+						Motion::Up => buf.row_up_at(pos, n),
+						Motion::Down => buf.row_down_at(pos, n),
+						_ => buf.motion_repeat(pos, motion, count),
+					}.unwrap_or(pos);
+				});
+
+				if self.cursor == before {
+					return Feed::Failed;
+				}
+
+				if motion.is_jump() {
+					self.push_jump(before);
+				}
+
+			},
+
+			Action::Operate(op, motion) => {
+
+				// like vim, cw changes to the end of the word
+				let motion = match (op, motion) {
+					(Operator::Change, Motion::NextWord) => Motion::WordEnd,
+					_ => motion,
+				};
+
+				match self.motion_range(self.cursor, motion, count) {
+					Some((r, linewise)) => self.operate(op, r, linewise),
+					None => return Feed::Failed,
+				}
+
+			},
+
+			Action::OperateObject(op, obj, inner) => {
+				match self.text_object(obj, inner) {
+					Some(r) => self.operate(op, r, obj.is_linewise()),
+					None => return Feed::Failed,
+				}
+			},
+
+			Action::OperateSelection(op) => self.operate_selection(op),
+
+			Action::SelectObject(obj, inner) => {
+
+				let r = match self.text_object(obj, inner) {
+					Some(r) => r,
+					None => return Feed::Failed,
+				};
+
+				let kind = if obj.is_linewise() {
+					SelectKind::Line
+				} else {
+					SelectKind::Char
+				};
+
+				self.mode = Mode::Select {
+					kind: kind,
+					anchor: r.start,
+				};
+
+				self.move_to(r.end);
+
+			},
+
+			Action::Insert => {
+				self.start_insert();
+				self.move_left();
+			},
+
+			Action::Append => self.start_insert(),
+			Action::InsertLineStart => self.move_line_start_insert(),
+			Action::AppendLineEnd => self.move_line_end_insert(),
+
+			Action::OpenBelow | Action::OpenAbove => {
+
+				let below = action == Action::OpenBelow;
+
+				self.push_undo();
+				self.mode = Mode::Insert;
+				self.for_each_cursor(|buf, pos| {
+					if below {
+						return buf.insert_str_at(Pos::new(pos.line, buf.line_len(pos.line) + 1), "\n");
+					} else {
+						buf.insert_str_at(Pos::new(pos.line, 1), "\n");
+						return Pos::new(pos.line, 1);
+					}
+				});
+
+			},
+
+			Action::DelChar => {
+				self.for_each_cursor(|buf, pos| {
+					// there's nothing to delete, the line break isn't a char here
+					if buf.line_len(pos.line) == 0 {
+						return pos;
+					}
+					let end = cmp::min(pos.col + n as Col - 1, buf.line_len(pos.line));
+					let r = Range {
+						start: pos,
+						end: Pos {
+							col: end,
+							.. pos
+						},
+					};
+					buf.yank_range(r);
+					return buf.del_range(r);
+				});
+			},
+
+			Action::Record(name) => {
+				if !name.is_ascii_alphabetic() {
+					return Feed::Invalid;
+				}
+				self.start_recording(name);
+			},
+
+			Action::Replay(name) => return self.replay_macro(name, n),
+
+			Action::SetMark(name) => {
+				if !self.set_mark(name) {
+					return Feed::Invalid;
+				}
+			},
+
+			Action::JumpBack => {
+				for _ in 0..n {
+					if !self.jump_back() {
+						return Feed::Failed;
+					}
+				}
+			},
+
+			Action::FoldToggle => {
+				match self.toggle_fold_at(self.cursor.line) {
+					Some(ln) => self.move_to(self.line_start_at(Pos::new(ln, 1))),
+					None => return Feed::Failed,
+				}
+			},
+
+			Action::FoldOpen => self.open_fold_at(self.cursor.line),
+
+			Action::FoldClose => {
+				for _ in 0..n {
+					match self.close_fold_at(self.cursor.line) {
+						Some(ln) => self.move_to(self.line_start_at(Pos::new(ln, 1))),
+						None => return Feed::Failed,
+					}
+				}
+			},
+
+			Action::FoldOpenAll => self.open_all_folds(),
+
+			Action::FoldCloseAll => {
+				self.close_all_folds();
+				self.move_to(self.line_start_at(Pos::new(self.shown_line_at(self.cursor.line), 1)));
+			},
+
+			Action::JumpForward => {
+				for _ in 0..n {
+					if !self.jump_forward() {
+						return Feed::Failed;
+					}
+				}
+			},
+
+			Action::Paste => {
+				for _ in 0..n {
+					self.paste();
+				}
+			},
+
+			Action::Undo => {
+				for _ in 0..n {
+					self.undo();
+				}
+			},
+
+			Action::Redo => {
+				for _ in 0..n {
+					self.redo();
+				}
+			},
+
+			Action::Earlier => self.earlier(n),
+			Action::Later => self.later(n),
+
+			Action::PrevBranch => {
+				for _ in 0..n {
+					self.prev_branch();
+				}
+			},
+
+			Action::NextBranch => {
+				for _ in 0..n {
+					self.next_branch();
+				}
+			},
+
+			Action::Select(kind) => self.start_select(kind),
+			Action::Command => self.start_command(),
+			Action::Search => self.start_search(),
+
+			Action::AddCursorAbove => {
+				for _ in 0..n {
+					self.add_cursor_above();
+				}
+			},
+
+			Action::AddCursorBelow => {
+				for _ in 0..n {
+					self.add_cursor_below();
+				}
+			},
+
+			Action::AddCursorNextWord => {
+				for _ in 0..n {
+					self.add_cursor_next_word();
+				}
+			},
+
+			Action::Reset => {
+				if self.select_kind().is_some() {
+					self.end_select();
+				} else {
+					self.reset();
+				}
+			},
+
+		}
+
+		return Feed::Done;
+
+	}
+
+}
+
+#[cfg(test)]
+mod tests {
+
+	use super::*;
+
+	fn keys(s: &str) -> Vec<Key> {
+		return s.chars().map(Key::Char).collect();
+	}
+
+	fn parse(s: &str) -> Parse {
+		return parse_keys(&keys(s), false);
+	}
+
+	#[test]
+	fn count_before_operator() {
+		assert_eq!(parse("3dw"), Parse::Complete {
+			action: Action::Operate(Operator::Delete, Motion::NextWord),
+			count: Some(3),
+			register: None,
+		});
+	}
+
+	#[test]
+	fn count_before_motion() {
+		assert_eq!(parse("d2j"), Parse::Complete {
+			action: Action::Operate(Operator::Delete, Motion::Down),
+			count: Some(2),
+			register: None,
+		});
+		assert_eq!(parse("2d3w"), Parse::Complete {
+			action: Action::Operate(Operator::Delete, Motion::NextWord),
+			count: Some(6),
+			register: None,
+		});
+	}
+
+	#[test]
+	fn text_object() {
+		assert_eq!(parse("ci("), Parse::Complete {
+			action: Action::OperateObject(Operator::Change, TextObject::Pair('('), true),
+			count: None,
+			register: None,
+		});
+	}
+
+	#[test]
+	fn register_prefix() {
+		assert_eq!(parse("\"ayy"), Parse::Complete {
+			action: Action::Operate(Operator::Yank, Motion::Line),
+			count: None,
+			register: Some('a'),
+		});
+	}
+
+	#[test]
+	fn invalid() {
+		assert_eq!(parse("dz"), Parse::Invalid);
+		assert_eq!(parse("Z"), Parse::Invalid);
+		assert_eq!(parse_keys(&[Key::Char('"'), Key::Escape], false), Parse::Invalid);
+	}
+
+	#[test]
+	fn pending() {
+		assert_eq!(parse("\""), Parse::Incomplete);
+		assert_eq!(parse("\"a"), Parse::Incomplete);
+		assert_eq!(parse("2d"), Parse::Incomplete);
+		assert_eq!(parse("ci"), Parse::Incomplete);
+		assert_eq!(parse("df"), Parse::Incomplete);
+	}
+
+}
+
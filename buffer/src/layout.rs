@@ -0,0 +1,330 @@
+// wengwengweng
+
+use std::cmp;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::*;
+
+/// how lines are split into rows of display cells
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+	/// cells in a row, 0 for no limit
+	pub width: u32,
+	pub tab_width: u32,
+	/// break long lines into more rows
+	pub wrap: bool,
+	/// cells taken by the prefix of a wrapped row
+	pub prefix_width: u32,
+}
+
+impl Default for Layout {
+	fn default() -> Self {
+		return Self {
+			width: 0,
+			tab_width: 4,
+			wrap: false,
+			prefix_width: 0,
+		};
+	}
+}
+
+/// a row of a line on screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Row {
+	/// the first col in the row
+	pub start: Col,
+	/// the col after the last one in the row
+	pub end: Col,
+	/// the display cell of the first col, counted from the line start
+	pub shift: u32,
+}
+
+impl Layout {
+
+	/// get the display cell and width of every grapheme in a line, cells start at 1
+	pub fn cells(&self, line: &str) -> Vec<(u32, u32)> {
+
+		let mut shift = 1;
+		let mut cells = vec![];
+
+		for g in line.graphemes(true) {
+
+			let w = if g == "\t" {
+				self.tab_width - (shift - 1) % self.tab_width
+			} else {
+				grapheme_width(g)
+			};
+
+			cells.push((shift, w));
+			shift += w;
+
+		}
+
+		return cells;
+
+	}
+
+	/// split a line into rows, a line always has at least one
+	pub fn rows(&self, line: &str) -> Vec<Row> {
+
+		let cells = self.cells(line);
+		let end = cells.len() as Col + 1;
+
+		if !self.wrap || self.width == 0 {
+			return vec![Row {
+				start: 1,
+				end: end,
+				shift: 1,
+			}];
+		}
+
+		let rest = cmp::max(self.width.saturating_sub(self.prefix_width), 1);
+		let mut rows = vec![];
+		let mut row = Row {
+			start: 1,
+			end: end,
+			shift: 1,
+		};
+
+		for (i, (shift, w)) in cells.iter().enumerate() {
+
+			let col = i as Col + 1;
+			let avail = if rows.is_empty() {
+				self.width
+			} else {
+				rest
+			};
+
+			if col > row.start && shift + w - row.shift > avail {
+				rows.push(Row {
+					end: col,
+					.. row
+				});
+				row = Row {
+					start: col,
+					end: end,
+					shift: *shift,
+				};
+			}
+
+		}
+
+		rows.push(row);
+
+		return rows;
+
+	}
+
+	/// get the cells before the text of a row
+	fn indent_of(&self, row: usize) -> u32 {
+		if row == 0 {
+			return 0;
+		} else {
+			return self.prefix_width;
+		}
+	}
+
+}
+
+impl Buffer {
+
+	/// get the rows of a line with the layout of the buffer
+	pub fn line_rows(&self, ln: Line) -> Vec<Row> {
+		return self.layout.rows(&self.get_line_at(ln).unwrap_or_default());
+	}
+
+	/// get which row of its line a position is on, and the display cell in the row (from 1, the wrap prefix included)
+	pub fn visual_pos(&self, pos: Pos) -> (usize, u32) {
+
+		let line = self.get_line_at(pos.line).unwrap_or_default();
+		let cells = self.layout.cells(&line);
+		let rows = self.layout.rows(&line);
+		let row = rows
+			.iter()
+			.position(|r| pos.col < r.end)
+			.unwrap_or(rows.len() - 1);
+
+		let shift = match cells.get(pos.col.saturating_sub(1) as usize) {
+			Some((shift, _)) => *shift,
+			None => cells.last().map(|(s, w)| s + w).unwrap_or(1),
+		};
+
+		return (row, shift - rows[row].shift + 1 + self.layout.indent_of(row));
+
+	}
+
+	/// get the col at a display cell in a row of a line, past the end of the row is its last col
+	pub fn col_at_visual(&self, ln: Line, row: usize, x: u32) -> Col {
+
+		let line = self.get_line_at(ln).unwrap_or_default();
+		let cells = self.layout.cells(&line);
+		let rows = self.layout.rows(&line);
+		let r = rows[cmp::min(row, rows.len() - 1)];
+		let indent = self.layout.indent_of(row);
+		let target = (x.saturating_sub(indent + 1)) + r.shift;
+
+		for col in r.start..r.end {
+			let (shift, w) = cells[col as usize - 1];
+			if target < shift + w {
+				return col;
+			}
+		}
+
+		return cmp::max(r.end - 1, r.start);
+
+	}
+
+	/// get the number of rows of the visible lines from a line up to another (exclusive), negative if it's above
+	pub fn visual_rows_between(&self, from: Line, to: Line) -> i32 {
+
+		if to < from {
+			return -self.visual_rows_between(to, from);
+		}
+
+		let folds = self.closed();
+
+		return (from..to)
+			.filter(|ln| !folds.hides(*ln))
+			.map(|ln| self.line_rows(ln).len() as i32)
+			.sum();
+
+	}
+
+	/// get the row a position is on counted from the first row of a line
+	pub fn visual_row_from(&self, from: Line, pos: Pos) -> i32 {
+
+		let shown = self.shown_line_at(pos.line);
+		let row = if shown == pos.line {
+			self.visual_pos(pos).0 as i32
+		} else {
+			0
+		};
+
+		return self.visual_rows_between(from, shown) + row;
+
+	}
+
+	/// get the position at a row counted from the first row of a line, and a display cell in it
+	pub fn visual_to_pos(&self, from: Line, row: usize, x: u32) -> Pos {
+
+		let mut ln = from;
+		let mut row = row;
+
+		loop {
+
+			let rows = self.line_rows(ln).len();
+
+			if row < rows {
+				break;
+			}
+
+			let next = self.visible_line_down(ln, 1);
+
+			if next == ln {
+				row = rows - 1;
+				break;
+			}
+
+			row -= rows;
+			ln = next;
+
+		}
+
+		return Pos::new(ln, self.col_at_visual(ln, row, x));
+
+	}
+
+	/// get the last line that starts within a number of rows from a line
+	pub fn line_after_rows(&self, from: Line, rows: usize) -> Line {
+
+		let mut ln = from;
+		let mut count = self.line_rows(ln).len();
+
+		while count < rows {
+
+			let next = self.visible_line_down(ln, 1);
+
+			if next == ln {
+				break;
+			}
+
+			ln = next;
+			count += self.line_rows(ln).len();
+
+		}
+
+		return ln;
+
+	}
+
+	/// get the position n rows below, keeping the display cell
+	pub fn row_down_at(&self, pos: Pos, n: usize) -> Option<Pos> {
+
+		let (mut row, x) = self.visual_pos(pos);
+		let mut ln = pos.line;
+
+		for _ in 0..n {
+
+			if row + 1 < self.line_rows(ln).len() {
+				row += 1;
+				continue;
+			}
+
+			let next = self.visible_line_down(ln, 1);
+
+			if next == ln {
+				break;
+			}
+
+			ln = next;
+			row = 0;
+
+		}
+
+		let target = Pos::new(ln, self.col_at_visual(ln, row, x));
+
+		if target == pos {
+			return None;
+		}
+
+		return Some(target);
+
+	}
+
+	/// get the position n rows above, keeping the display cell
+	pub fn row_up_at(&self, pos: Pos, n: usize) -> Option<Pos> {
+
+		let (mut row, x) = self.visual_pos(pos);
+		let mut ln = pos.line;
+
+		for _ in 0..n {
+
+			if row > 0 {
+				row -= 1;
+				continue;
+			}
+
+			let prev = self.visible_line_up(ln, 1);
+
+			if prev == ln {
+				break;
+			}
+
+			ln = prev;
+			row = self.line_rows(ln).len() - 1;
+
+		}
+
+		let target = Pos::new(ln, self.col_at_visual(ln, row, x));
+
+		if target == pos {
+			return None;
+		}
+
+		return Some(target);
+
+	}
+
+}
+
@@ -2,11 +2,50 @@
 
 mod buffer;
 mod ft;
+mod ftdef;
 mod syntax;
-mod ft_test;
+mod filetypes;
+mod unicode;
+mod undo;
+mod utils;
+mod select;
+mod search;
+mod substitute;
+mod command;
+mod keys;
+mod textobj;
+mod register;
+mod macros;
+mod marks;
+mod brackets;
+mod fold;
+mod layout;
+mod encoding;
+mod save;
+mod error;
+mod merge;
+mod watch;
 
 pub use buffer::*;
 pub use ft::*;
+pub use ftdef::*;
 pub use syntax::*;
+pub use unicode::*;
+pub use undo::*;
+pub use select::*;
+pub use search::*;
+pub use substitute::*;
+pub use command::*;
+pub use keys::*;
+pub use textobj::*;
+pub use register::*;
+pub use macros::*;
+pub use fold::*;
+pub use layout::*;
+pub use encoding::*;
+pub use save::*;
+pub use error::*;
+pub use merge::*;
+pub(crate) use watch::*;
 
 
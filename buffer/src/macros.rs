@@ -0,0 +1,279 @@
+// wengwengweng
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::collections::BTreeMap;
+
+use super::*;
+
+/// how deep macros can call other macros
+const MAX_MACRO_DEPTH: usize = 32;
+
+/// keys being recorded into a register
+#[derive(Clone, Debug)]
+pub(crate) struct Recording {
+	register: char,
+	keys: Vec<Key>,
+}
+
+const KEY_NAMES: [(Key, &str); 9] = [
+	(Key::Enter, "CR"),
+	(Key::Escape, "Esc"),
+	(Key::Backspace, "BS"),
+	(Key::Tab, "Tab"),
+	(Key::Up, "Up"),
+	(Key::Down, "Down"),
+	(Key::Left, "Left"),
+	(Key::Right, "Right"),
+	(Key::Char('<'), "lt"),
+];
+
+/// write keys as text, special keys are written like <Esc> or <C-w>
+pub fn keys_to_string(keys: &[Key]) -> String {
+
+	let mut s = String::new();
+
+	for key in keys {
+
+		if let Some((_, name)) = KEY_NAMES.iter().find(|(k, _)| k == key) {
+			s.push_str(&format!("<{}>", name));
+			continue;
+		}
+
+		match key {
+			Key::Char(ch) => s.push(*ch),
+			Key::Ctrl(ch) => s.push_str(&format!("<C-{}>", ch)),
+			_ => {},
+		}
+
+	}
+
+	return s;
+
+}
+
+/// read keys written by keys_to_string(), a < that doesn't start a key name is kept as it is
+pub fn parse_key_string(s: &str) -> Vec<Key> {
+
+	let mut keys = vec![];
+	let mut rest = s;
+
+	while let Some(ch) = rest.chars().next() {
+
+		if ch == '<' {
+			if let Some(end) = rest.find('>') {
+				if let Some(key) = key_of_name(&rest[1..end]) {
+					keys.push(key);
+					rest = &rest[end + 1..];
+					continue;
+				}
+			}
+		}
+
+		keys.push(Key::Char(ch));
+		rest = &rest[ch.len_utf8()..];
+
+	}
+
+	return keys;
+
+}
+
+fn key_of_name(name: &str) -> Option<Key> {
+
+	if let Some((key, _)) = KEY_NAMES.iter().find(|(_, n)| n.eq_ignore_ascii_case(name)) {
+		return Some(*key);
+	}
+
+	if name.starts_with("C-") || name.starts_with("c-") {
+		let mut chars = name[2..].chars();
+		return match (chars.next(), chars.next()) {
+			(Some(ch), None) => Some(Key::Ctrl(ch)),
+			_ => None,
+		};
+	}
+
+	return None;
+
+}
+
+/// where macros are kept between sessions in the user data dir
+pub fn macro_file_path() -> Option<PathBuf> {
+	return Some(dirs::data_dir()?.join("hop").join("macros.json"));
+}
+
+impl Buffer {
+
+	/// start recording fed keys into a register
+	pub fn start_recording(&mut self, name: char) {
+		self.recording = Some(Recording {
+			register: name,
+			keys: vec![],
+		});
+	}
+
+	/// stop recording and write the keys to the register, an uppercase register appends
+	pub fn stop_recording(&mut self) {
+		if let Some(rec) = self.recording.take() {
+			self.registers.store_recording(rec.register, Register::new(&keys_to_string(&rec.keys), RegisterKind::Char));
+			self.last_macro = Some(rec.register.to_ascii_lowercase());
+			self.persist_macros();
+		}
+	}
+
+	/// get the register keys are being recorded into
+	pub fn recording(&self) -> Option<char> {
+		return self.recording.as_ref().map(|r| r.register);
+	}
+
+	/// keep a key fed while recording, the q that stops it is left out
+	pub(crate) fn record_key(&mut self, key: Key) -> bool {
+
+		if self.macro_depth > 0 {
+			return false;
+		}
+
+		let stops = match self.mode {
+			Mode::Normal | Mode::Select { .. } => key == Key::Char('q') && self.pending_keys.is_empty(),
+			_ => false,
+		};
+
+		if self.recording.is_some() && stops {
+			self.stop_recording();
+			return true;
+		}
+
+		if let Some(rec) = &mut self.recording {
+			rec.keys.push(key);
+		}
+
+		return false;
+
+	}
+
+	/// feed the keys in a register a number of times, stops when a key fails, @ is the last macro run
+	pub fn replay_macro(&mut self, name: char, times: usize) -> Feed {
+
+		let name = if name == '@' {
+			match self.last_macro {
+				Some(name) => name,
+				None => return Feed::Invalid,
+			}
+		} else {
+			name
+		};
+
+		let keys = match self.registers.get(Some(name)) {
+			Some(reg) => parse_key_string(&reg.text),
+			None => return Feed::Invalid,
+		};
+
+		if self.macro_depth >= MAX_MACRO_DEPTH {
+			return Feed::Failed;
+		}
+
+		self.last_macro = Some(name);
+		self.macro_depth += 1;
+		self.push_undo();
+		self.begin_undo_group();
+
+		let mut result = Feed::Done;
+
+		'replay: for _ in 0..times {
+			for key in &keys {
+				match self.feed(*key) {
+					Feed::Failed | Feed::Invalid | Feed::Command(Err(_)) => {
+						result = Feed::Failed;
+						break 'replay;
+					},
+					_ => {},
+				}
+			}
+		}
+
+		self.pending_keys.clear();
+		self.end_undo_group();
+		self.macro_depth -= 1;
+
+		return result;
+
+	}
+
+	/// add the macros recorded in this session to a file, the ones already in it are kept
+	pub fn save_macros(&self, path: &Path) -> Result<(), Error> {
+
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)
+				.map_err(|e| Error::from_io(dir, FileOp::Write, e))?;
+		}
+
+		let mut data: BTreeMap<char, Register> = match fs::read_to_string(path) {
+			// a file we can't make sense of is written over
+			Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+			Err(e) => return Err(Error::from_io(path, FileOp::Read, e)),
+		};
+
+		for (name, reg) in self.registers.recorded() {
+			data.insert(name, reg.clone());
+		}
+
+		let json = serde_json::to_string_pretty(&data)
+			.map_err(|e| Error::Write(path.to_path_buf(), io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+		return write_atomic(path, json.as_bytes())
+			.map_err(|e| Error::from_io(path, FileOp::Write, e));
+
+	}
+
+	/// read named registers written by save_macros()
+	pub fn load_macros(&mut self, path: &Path) -> Result<(), Error> {
+
+		let json = fs::read_to_string(path)
+			.map_err(|e| Error::from_io(path, FileOp::Read, e))?;
+		let data: BTreeMap<char, Register> = serde_json::from_str(&json)
+			.map_err(|e| Error::Read(path.to_path_buf(), io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+		for (name, reg) in data {
+			if name.is_ascii_lowercase() {
+				self.registers.store(name, reg);
+			}
+		}
+
+		return Ok(());
+
+	}
+
+	/// read the macros kept from earlier sessions, no file yet is fine
+	pub fn restore_macros(&mut self) {
+
+		let path = match macro_file_path() {
+			Some(path) => path,
+			None => return,
+		};
+
+		match self.load_macros(&path) {
+			Ok(()) | Err(Error::NotFound(_, _)) => {},
+			Err(e) => self.log(&format!("failed to load macros: {}", e)),
+		}
+
+	}
+
+	/// keep the macros recorded in this session for later sessions
+	pub fn persist_macros(&mut self) {
+
+		let path = match macro_file_path() {
+			Some(path) => path,
+			None => return,
+		};
+
+		if let Err(e) = self.save_macros(&path) {
+			self.log(&format!("failed to save macros: {}", e));
+		}
+
+	}
+
+}
+
@@ -0,0 +1,144 @@
+// wengwengweng
+
+use std::cmp;
+
+use super::*;
+
+/// how many positions the jump list keeps
+const MAX_JUMPS: usize = 100;
+
+/// if a mark can be set by name
+///
+/// - `a` - `z`, `A` - `Z` set with m
+/// - `<` / `>` the start and end of the last selection
+fn is_mark_name(name: char) -> bool {
+	return name.is_ascii_alphabetic() || name == '<' || name == '>';
+}
+
+impl Buffer {
+
+	/// set a mark at a position, returns false for names that can't be set
+	pub fn set_mark_at(&mut self, name: char, pos: Pos) -> bool {
+
+		if !is_mark_name(name) {
+			return false;
+		}
+
+		return match self.pos_to_char(pos) {
+			Some(idx) => {
+				self.marks.insert(name, idx);
+				true
+			},
+			None => false,
+		};
+
+	}
+
+	/// set a mark at the cursor
+	pub fn set_mark(&mut self, name: char) -> bool {
+		return self.set_mark_at(name, self.cursor);
+	}
+
+	/// get the position of a mark, ' and ` are where the last jump started
+	pub fn mark(&self, name: char) -> Option<Pos> {
+
+		let idx = match name {
+			'\'' | '`' => *self.jumps.last()?,
+			_ => *self.marks.get(&name)?,
+		};
+
+		return Some(self.char_to_pos(cmp::min(idx, self.content.len_chars())));
+
+	}
+
+	/// remove a mark
+	pub fn del_mark(&mut self, name: char) {
+		self.marks.remove(&name);
+	}
+
+	/// get every mark set, sorted by name
+	pub fn marks(&self) -> Vec<(char, Pos)> {
+
+		let mut marks: Vec<(char, Pos)> = self.marks
+			.keys()
+			.filter_map(|c| self.mark(*c).map(|p| (*c, p)))
+			.collect();
+
+		marks.sort_by_key(|(c, _)| *c);
+
+		return marks;
+
+	}
+
+	/// remember a position before a jump, an older entry on the same line is dropped
+	pub fn push_jump(&mut self, pos: Pos) {
+
+		let idx = match self.pos_to_char(pos) {
+			Some(idx) => idx,
+			None => return,
+		};
+
+		let content = &self.content;
+		let line = content.char_to_line(idx);
+
+		self.jumps.retain(|i| content.char_to_line(cmp::min(*i, content.len_chars())) != line);
+		self.jumps.push(idx);
+
+		if self.jumps.len() > MAX_JUMPS {
+			self.jumps.remove(0);
+		}
+
+		self.jump_index = self.jumps.len();
+
+	}
+
+	/// get the positions in the jump list, oldest first
+	pub fn jumps(&self) -> Vec<Pos> {
+		let len = self.content.len_chars();
+		return self.jumps.iter().map(|i| self.char_to_pos(cmp::min(*i, len))).collect();
+	}
+
+	/// go to an older position in the jump list, returns false if there is none
+	pub fn jump_back(&mut self) -> bool {
+
+		// keep where we are so jump_forward() can come back
+		if self.jump_index >= self.jumps.len() {
+			self.push_jump(self.cursor);
+			self.jump_index = self.jumps.len() - 1;
+		}
+
+		if self.jump_index == 0 {
+			return false;
+		}
+
+		self.jump_index -= 1;
+		self.goto_jump();
+
+		return true;
+
+	}
+
+	/// go to a newer position in the jump list, returns false if there is none
+	pub fn jump_forward(&mut self) -> bool {
+
+		if self.jump_index + 1 >= self.jumps.len() {
+			return false;
+		}
+
+		self.jump_index += 1;
+		self.goto_jump();
+
+		return true;
+
+	}
+
+	fn goto_jump(&mut self) {
+		if let Some(idx) = self.jumps.get(self.jump_index) {
+			let pos = self.char_to_pos(cmp::min(*idx, self.content.len_chars()));
+			self.child_cursors.clear();
+			self.move_to(pos);
+		}
+	}
+
+}
+
@@ -0,0 +1,188 @@
+// wengwengweng
+
+/// the result of a three-way merge
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merged {
+	pub text: String,
+	/// how many places both sides changed differently, marked with <<<<<<< / ======= / >>>>>>>
+	pub conflicts: usize,
+}
+
+/// find the middle snake of the shortest edit script between a and b, as the start and end of it in both
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize, usize, usize) {
+
+	let (n, m) = (a.len() as isize, b.len() as isize);
+	let max = (n + m + 1) / 2;
+	let delta = n - m;
+	let off = max + 1;
+
+	// the furthest x on every diagonal, going forward from the start and backward from the end
+	let mut fwd = vec![0isize; (2 * max + 3) as usize];
+	let mut bwd = vec![0isize; (2 * max + 3) as usize];
+	let at = |k: isize| (off + k) as usize;
+
+	for d in 0..=max {
+
+		for k in (-d..=d).step_by(2) {
+
+			let mut x = if k == -d || (k != d && fwd[at(k - 1)] < fwd[at(k + 1)]) {
+				fwd[at(k + 1)]
+			} else {
+				fwd[at(k - 1)] + 1
+			};
+
+			let mut y = x - k;
+			let (sx, sy) = (x, y);
+
+			while x < n && y < m && a[x as usize] == b[y as usize] {
+				x += 1;
+				y += 1;
+			}
+
+			fwd[at(k)] = x;
+
+			let kb = delta - k;
+
+			if delta % 2 != 0 && kb >= -(d - 1) && kb < d && x + bwd[at(kb)] >= n {
+				return (sx as usize, sy as usize, x as usize, y as usize);
+			}
+
+		}
+
+		for k in (-d..=d).step_by(2) {
+
+			let mut x = if k == -d || (k != d && bwd[at(k - 1)] < bwd[at(k + 1)]) {
+				bwd[at(k + 1)]
+			} else {
+				bwd[at(k - 1)] + 1
+			};
+
+			let mut y = x - k;
+			let (sx, sy) = (x, y);
+
+			while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+				x += 1;
+				y += 1;
+			}
+
+			bwd[at(k)] = x;
+
+			let kf = delta - k;
+
+			if delta % 2 == 0 && kf >= -d && kf <= d && x + fwd[at(kf)] >= n {
+				return ((n - x) as usize, (m - y) as usize, (n - sx) as usize, (m - sy) as usize);
+			}
+
+		}
+
+	}
+
+	return (0, 0, 0, 0);
+
+}
+
+/// match the lines of a with the lines of b in a longest common subsequence, in linear space
+fn diff_lines(a: &[&str], b: &[&str], a_off: usize, b_off: usize, matched: &mut Vec<Option<usize>>) {
+
+	// the same start and end don't need a search
+	let mut start = 0;
+
+	while start < a.len() && start < b.len() && a[start] == b[start] {
+		matched[a_off + start] = Some(b_off + start);
+		start += 1;
+	}
+
+	let mut end = 0;
+
+	while end < a.len() - start && end < b.len() - start && a[a.len() - 1 - end] == b[b.len() - 1 - end] {
+		matched[a_off + a.len() - 1 - end] = Some(b_off + b.len() - 1 - end);
+		end += 1;
+	}
+
+	let a = &a[start..a.len() - end];
+	let b = &b[start..b.len() - end];
+	let (a_off, b_off) = (a_off + start, b_off + start);
+
+	if a.is_empty() || b.is_empty() {
+		return;
+	}
+
+	let (x, y, u, v) = middle_snake(a, b);
+
+	diff_lines(&a[..x], &b[..y], a_off, b_off, matched);
+
+	for i in 0..u - x {
+		matched[a_off + x + i] = Some(b_off + y + i);
+	}
+
+	diff_lines(&a[u..], &b[v..], a_off + u, b_off + v, matched);
+
+}
+
+/// for every line in a, the line in b it's matched with in a longest common subsequence
+fn match_lines(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+
+	let mut matched = vec![None; a.len()];
+
+	diff_lines(a, b, 0, 0, &mut matched);
+
+	return matched;
+
+}
+
+/// merge the changes two sides made to the same base, line by line
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> Merged {
+
+	let base: Vec<&str> = base.split('\n').collect();
+	let ours: Vec<&str> = ours.split('\n').collect();
+	let theirs: Vec<&str> = theirs.split('\n').collect();
+
+	let to_ours = match_lines(&base, &ours);
+	let to_theirs = match_lines(&base, &theirs);
+
+	let mut lines: Vec<&str> = vec![];
+	let mut conflicts = 0;
+	let (mut i, mut j, mut k) = (0, 0, 0);
+
+	loop {
+
+		// the next base line both sides kept
+		let sync = (i..base.len()).find_map(|b| match (to_ours[b], to_theirs[b]) {
+			(Some(o), Some(t)) if o >= j && t >= k => Some((b, o, t)),
+			_ => None,
+		});
+
+		let (b, o, t) = sync.unwrap_or((base.len(), ours.len(), theirs.len()));
+		let (base_part, ours_part, theirs_part) = (&base[i..b], &ours[j..o], &theirs[k..t]);
+
+		if ours_part == base_part {
+			lines.extend(theirs_part);
+		} else if theirs_part == base_part || ours_part == theirs_part {
+			lines.extend(ours_part);
+		} else {
+			conflicts += 1;
+			lines.push("<<<<<<< ours");
+			lines.extend(ours_part);
+			lines.push("=======");
+			lines.extend(theirs_part);
+			lines.push(">>>>>>> disk");
+		}
+
+		if sync.is_none() {
+			break;
+		}
+
+		lines.push(base[b]);
+		i = b + 1;
+		j = o + 1;
+		k = t + 1;
+
+	}
+
+	return Merged {
+		text: lines.join("\n"),
+		conflicts: conflicts,
+	};
+
+}
+
@@ -0,0 +1,354 @@
+// wengwengweng
+
+use std::fmt;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use serde::Deserialize;
+
+#[cfg(feature = "clipboard")]
+use clipboard::ClipboardProvider;
+#[cfg(feature = "clipboard")]
+use clipboard::ClipboardContext;
+
+use super::*;
+
+const RING_SIZE: usize = 10;
+
+/// why text couldn't be copied to a clipboard
+#[derive(Clone, Debug)]
+pub struct ClipboardError {
+	pub msg: String,
+}
+
+impl fmt::Display for ClipboardError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		return write!(f, "failed to copy to clipboard: {}", self.msg);
+	}
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// a place to copy text to and paste it from, outside of the buffer
+pub trait Clipboard {
+	fn get(&mut self) -> Option<String>;
+	fn set(&mut self, text: &str) -> Result<(), ClipboardError>;
+}
+
+/// a clipboard that only lives as long as the editor
+#[derive(Clone, Debug, Default)]
+pub struct MemClipboard {
+	text: Option<String>,
+}
+
+impl MemClipboard {
+	pub fn new() -> Self {
+		return Self::default();
+	}
+}
+
+impl Clipboard for MemClipboard {
+
+	fn get(&mut self) -> Option<String> {
+		return self.text.clone();
+	}
+
+	fn set(&mut self, text: &str) -> Result<(), ClipboardError> {
+		self.text = Some(String::from(text));
+		return Ok(());
+	}
+
+}
+
+/// the clipboard of the system
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard {
+	ctx: ClipboardContext,
+}
+
+#[cfg(feature = "clipboard")]
+impl SystemClipboard {
+
+	/// connect to the system clipboard, None if there is none like on a headless machine
+	pub fn new() -> Option<Self> {
+		return ClipboardProvider::new().ok().map(|ctx| Self {
+			ctx: ctx,
+		});
+	}
+
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard for SystemClipboard {
+
+	fn get(&mut self) -> Option<String> {
+		return self.ctx.get_contents().ok();
+	}
+
+	fn set(&mut self, text: &str) -> Result<(), ClipboardError> {
+		return self.ctx.set_contents(String::from(text)).map_err(|e| ClipboardError {
+			msg: format!("{}", e),
+		});
+	}
+
+}
+
+/// get the system clipboard if there is one, or a clipboard in memory
+pub fn default_clipboard() -> Box<dyn Clipboard> {
+
+	#[cfg(feature = "clipboard")]
+	{
+		if let Some(c) = SystemClipboard::new() {
+			return Box::new(c);
+		}
+	}
+
+	return Box::new(MemClipboard::new());
+
+}
+
+/// how register content is pasted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegisterKind {
+	/// pasted at the cursor
+	Char,
+	/// whole lines, pasted below the cursor line
+	Line,
+}
+
+/// the content of a register, whole lines don't end with a newline
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Register {
+	pub text: String,
+	pub kind: RegisterKind,
+}
+
+impl Register {
+
+	pub fn new(text: &str, kind: RegisterKind) -> Self {
+		return Self {
+			text: String::from(text),
+			kind: kind,
+		};
+	}
+
+	/// get the text as it goes to the clipboard, whole lines end with a newline
+	fn to_clipboard(&self) -> String {
+		return match self.kind {
+			RegisterKind::Char => self.text.clone(),
+			RegisterKind::Line => format!("{}\n", self.text),
+		};
+	}
+
+	/// read text from the clipboard, text ending with a newline is whole lines
+	fn from_clipboard(text: &str) -> Self {
+		if let Some(lines) = text.strip_suffix('\n') {
+			return Self::new(lines, RegisterKind::Line);
+		}
+		return Self::new(text, RegisterKind::Char);
+	}
+
+}
+
+/// the registers of a buffer
+///
+/// - `"` the unnamed register, every copy and delete goes here
+/// - `a` - `z` named registers, uppercase appends to them
+/// - `0` - `9` the yank ring, `0` is the newest
+/// - `+` / `*` the clipboard
+/// - `_` drops whatever is copied to it
+pub struct Registers {
+	unnamed: Option<Register>,
+	named: HashMap<char, Register>,
+	/// named registers that hold keys recorded in this session
+	recorded: HashSet<char>,
+	ring: VecDeque<Register>,
+	clipboard: Box<dyn Clipboard>,
+	/// copy to the clipboard too on every copy
+	pub sync_clipboard: bool,
+}
+
+impl Registers {
+
+	pub fn new(clipboard: Box<dyn Clipboard>) -> Self {
+		return Self {
+			unnamed: None,
+			named: HashMap::new(),
+			recorded: HashSet::new(),
+			ring: VecDeque::with_capacity(RING_SIZE),
+			clipboard: clipboard,
+			sync_clipboard: true,
+		};
+	}
+
+	/// replace the clipboard backend
+	pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+		self.clipboard = clipboard;
+	}
+
+	/// write only to a named register or the clipboard, without touching the unnamed register or the ring
+	pub fn store(&mut self, name: char, reg: Register) {
+
+		self.recorded.remove(&name.to_ascii_lowercase());
+
+		match name {
+
+			'a'..='z' => {
+				self.named.insert(name, reg);
+			},
+
+			'A'..='Z' => {
+
+				let c = name.to_ascii_lowercase();
+
+				let reg = match self.named.remove(&c) {
+					Some(prev) => {
+						let kind = if prev.kind == RegisterKind::Line || reg.kind == RegisterKind::Line {
+							RegisterKind::Line
+						} else {
+							RegisterKind::Char
+						};
+						let sep = if kind == RegisterKind::Line { "\n" } else { "" };
+						Register::new(&format!("{}{}{}", prev.text, sep, reg.text), kind)
+					},
+					None => reg,
+				};
+
+				self.named.insert(c, reg);
+
+			},
+
+			'+' | '*' => {
+				let _ = self.clipboard.set(&reg.to_clipboard());
+			},
+
+			_ => {},
+
+		}
+
+	}
+
+	/// write recorded keys to a named register, it's kept as a macro between sessions
+	pub fn store_recording(&mut self, name: char, reg: Register) {
+
+		self.store(name, reg);
+
+		if name.is_ascii_alphabetic() {
+			self.recorded.insert(name.to_ascii_lowercase());
+		}
+
+	}
+
+	/// copy to a register, or to the unnamed register if None
+	pub fn set(&mut self, name: Option<char>, reg: Register) {
+
+		match name {
+			Some('_') => return,
+			Some('+') | Some('*') => {},
+			_ if self.sync_clipboard => {
+				let _ = self.clipboard.set(&reg.to_clipboard());
+			},
+			_ => {},
+		}
+
+		if let Some(name) = name {
+			self.store(name, reg.clone());
+		}
+
+		if self.ring.len() >= RING_SIZE {
+			self.ring.pop_back();
+		}
+
+		self.ring.push_front(reg.clone());
+		self.unnamed = Some(reg);
+
+	}
+
+	/// get the content of a register, or of the unnamed register if None
+	pub fn get(&mut self, name: Option<char>) -> Option<Register> {
+
+		return match name {
+			None | Some('"') => self.unnamed.clone(),
+			Some(c @ 'a'..='z') | Some(c @ 'A'..='Z') => self.named.get(&c.to_ascii_lowercase()).cloned(),
+			Some(c @ '0'..='9') => self.ring.get(c as usize - '0' as usize).cloned(),
+			Some('+') | Some('*') => self.clipboard.get().map(|t| Register::from_clipboard(&t)),
+			_ => None,
+		};
+
+	}
+
+	/// get the named registers
+	pub fn named(&self) -> impl Iterator<Item = (char, &Register)> {
+		return self.named.iter().map(|(c, r)| (*c, r));
+	}
+
+	/// get the named registers keys were recorded into in this session
+	pub fn recorded(&self) -> impl Iterator<Item = (char, &Register)> {
+		return self.named
+			.iter()
+			.filter(move |(c, _)| self.recorded.contains(c))
+			.map(|(c, r)| (*c, r));
+	}
+
+	/// get the recent copies, newest first
+	pub fn history(&self) -> impl Iterator<Item = &Register> {
+		return self.ring.iter();
+	}
+
+}
+
+impl Buffer {
+
+	/// use a register for the next copy or paste
+	pub fn use_register(&mut self, name: char) {
+		self.register = Some(name);
+	}
+
+	/// copy text to the register in use
+	pub fn yank_text(&mut self, text: &str, kind: RegisterKind) {
+		self.registers.set(self.register, Register::new(text, kind));
+	}
+
+	/// get the text of whole lines (inclusive)
+	pub(crate) fn lines_text(&self, start: Line, end: Line) -> String {
+		return (start..=end)
+			.filter_map(|ln| self.get_line_at(ln))
+			.collect::<Vec<String>>()
+			.join("\n");
+	}
+
+	/// paste a register at a position, whole lines go below its line, returns where the cursor goes
+	pub fn paste_register_at(&mut self, pos: Pos, reg: &Register) -> Pos {
+
+		match reg.kind {
+
+			RegisterKind::Char => return self.insert_str_at(pos, &reg.text),
+
+			RegisterKind::Line => {
+
+				let idx = match self.line_to_char(pos.line) {
+					Some(idx) => idx + self.content.line(pos.line as usize - 1).len_chars(),
+					None => return pos,
+				};
+
+				self.push_undo();
+
+				// the last line has no newline to insert after
+				if idx > 0 && self.content.char(idx - 1) == '\n' {
+					self.edit_chars(idx, idx, &format!("{}\n", reg.text));
+				} else {
+					self.edit_chars(idx, idx, &format!("\n{}", reg.text));
+				}
+
+				return self.line_start_at(Pos::new(pos.line + 1, 1));
+
+			},
+
+		}
+
+	}
+
+}
+
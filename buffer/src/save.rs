@@ -0,0 +1,267 @@
+// wengwengweng
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::process;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::Deserialize;
+
+use super::*;
+
+/// how often unsaved changes are written to the swap file
+const SWAP_INTERVAL: Duration = Duration::from_secs(4);
+
+/// unsaved changes of a file, kept in case we crash
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SwapFile {
+	path: PathBuf,
+	cursor: Pos,
+	content: String,
+}
+
+/// where the swap file of a file is kept in the user data dir
+pub fn swap_file_path(path: &Path) -> Option<PathBuf> {
+
+	let dir = dirs::data_dir()?
+		.join("hop")
+		.join("swap");
+
+	return Some(dir.join(format!("{:016x}.json", utils::hash_path(path))));
+
+}
+
+/// where the backup of a file goes before it's overwritten
+pub fn backup_file_path(path: &Path) -> PathBuf {
+
+	let mut name = path.as_os_str().to_owned();
+
+	name.push("~");
+
+	return PathBuf::from(name);
+
+}
+
+/// write a file so it's either fully old or fully new, through a temp file in the same dir that's synced and renamed over it
+///
+/// files with other hard links, and ones in dirs we can't create the temp file in, are written in place instead
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+
+	let dir = match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new("."),
+	};
+
+	let name = path
+		.file_name()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?;
+
+	let meta = fs::metadata(path).ok();
+
+	// a rename gives the file a new inode, the other names would keep the old content
+	if meta.as_ref().map(link_count).unwrap_or(1) > 1 {
+		return write_in_place(path, bytes);
+	}
+
+	let tmp = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+
+	let mut file = match fs::File::create(&tmp) {
+		Ok(file) => file,
+		Err(_) => return write_in_place(path, bytes),
+	};
+
+	let result = (|| {
+
+		file.write_all(bytes)?;
+		file.sync_all()?;
+
+		if let Some(meta) = &meta {
+			fs::set_permissions(&tmp, meta.permissions())?;
+			keep_owner(&tmp, meta);
+		}
+
+		return fs::rename(&tmp, path);
+
+	})();
+
+	if result.is_err() {
+		let _ = fs::remove_file(&tmp);
+		return result;
+	}
+
+	// the rename is only on disk after the dir is synced, but the file is already saved if that fails
+	#[cfg(unix)]
+	{
+		if let Ok(dir) = fs::File::open(dir) {
+			let _ = dir.sync_all();
+		}
+	}
+
+	return Ok(());
+
+}
+
+/// overwrite a file where it is, keeping its inode
+fn write_in_place(path: &Path, bytes: &[u8]) -> io::Result<()> {
+
+	let mut file = fs::OpenOptions::new()
+		.write(true)
+		.create(true)
+		.truncate(true)
+		.open(path)?;
+
+	file.write_all(bytes)?;
+	file.sync_all()?;
+
+	return Ok(());
+
+}
+
+/// if a file can't be written without forcing it
+pub fn is_read_only(path: &Path) -> bool {
+	return fs::metadata(path)
+		.map(|m| m.permissions().readonly())
+		.unwrap_or(false);
+}
+
+#[cfg(unix)]
+fn link_count(meta: &fs::Metadata) -> u64 {
+	use std::os::unix::fs::MetadataExt;
+	return meta.nlink();
+}
+
+#[cfg(not(unix))]
+fn link_count(_: &fs::Metadata) -> u64 {
+	return 1;
+}
+
+#[cfg(unix)]
+fn keep_owner(path: &Path, meta: &fs::Metadata) {
+
+	use std::os::unix::fs::chown;
+	use std::os::unix::fs::MetadataExt;
+
+	// only root can give a file to another user, the group can still be kept
+	if chown(path, Some(meta.uid()), Some(meta.gid())).is_err() {
+		let _ = chown(path, None, Some(meta.gid()));
+	}
+
+}
+
+#[cfg(not(unix))]
+fn keep_owner(_: &Path, _: &fs::Metadata) {}
+
+impl Buffer {
+
+	/// write unsaved changes to the swap file, at most once every few seconds
+	pub fn update_swap(&mut self) {
+
+		if !self.swap || !self.modified {
+			return;
+		}
+
+		if let Some(time) = self.swap_time {
+			if time.elapsed() < SWAP_INTERVAL {
+				return;
+			}
+		}
+
+		self.swap_time = Some(Instant::now());
+
+		let hash = utils::hash(self.content.chunks());
+
+		if hash == self.swap_hash {
+			return;
+		}
+
+		match self.write_swap() {
+			Ok(_) => self.swap_hash = hash,
+			Err(e) => self.log(&format!("failed to write swap file: {}", e)),
+		}
+
+	}
+
+	/// write the content to the swap file now
+	pub fn write_swap(&self) -> Result<(), Error> {
+
+		let file = swap_file_path(&self.path)
+			.ok_or_else(|| Error::Write(self.path.clone(), io::Error::new(io::ErrorKind::NotFound, "no user data dir")))?;
+
+		if let Some(dir) = file.parent() {
+			fs::create_dir_all(dir)
+				.map_err(|e| Error::from_io(dir, FileOp::Write, e))?;
+		}
+
+		let data = SwapFile {
+			path: self.path.clone(),
+			cursor: self.cursor,
+			content: self.content.to_string(),
+		};
+
+		let json = serde_json::to_string(&data)
+			.map_err(|e| Error::Write(file.clone(), io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+		return write_atomic(&file, json.as_bytes())
+			.map_err(|e| Error::from_io(&file, FileOp::Write, e));
+
+	}
+
+	/// remove the swap file, after a save or when the changes are thrown away
+	pub fn delete_swap(&mut self) {
+
+		if let Some(file) = swap_file_path(&self.path) {
+			let _ = fs::remove_file(file);
+		}
+
+		self.swap_hash = utils::hash(self.content.chunks());
+		self.recovery = None;
+
+	}
+
+	/// look for a swap file left by a session that didn't save, one with the same content as the file is dropped
+	pub(crate) fn check_swap(&mut self) {
+
+		let data = swap_file_path(&self.path)
+			.and_then(|file| fs::read_to_string(file).ok())
+			.and_then(|json| serde_json::from_str::<SwapFile>(&json).ok());
+
+		if let Some(data) = data {
+			if data.content == self.content.to_string() {
+				self.delete_swap();
+			} else {
+				self.recovery = Some(data);
+			}
+		}
+
+	}
+
+	/// if there are unsaved changes from a swap file that can be recovered
+	pub fn swap_found(&self) -> bool {
+		return self.recovery.is_some();
+	}
+
+	/// replace the content with the one in the swap file, as a change that can be undone
+	pub fn recover_swap(&mut self) -> bool {
+
+		let data = match self.recovery.take() {
+			Some(data) => data,
+			None => return false,
+		};
+
+		self.push_undo();
+		self.edit_chars(0, self.content.len_chars(), &data.content);
+		self.push_undo();
+		self.child_cursors.clear();
+		self.move_to(data.cursor);
+
+		return true;
+
+	}
+
+}
+
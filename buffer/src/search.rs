@@ -0,0 +1,418 @@
+// wengwengweng
+
+use std::cmp;
+
+use regex::Regex;
+use regex::RegexBuilder;
+
+use super::*;
+
+/// how a search pattern is matched
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchOpts {
+	/// treat the pattern as a regex instead of plain text
+	pub regex: bool,
+	/// ignore case unless the pattern has an uppercase char
+	pub smart_case: bool,
+	/// only match whole words
+	pub whole_word: bool,
+	/// continue from the other end of the file when reaching the end
+	pub wrap: bool,
+}
+
+impl Default for SearchOpts {
+	fn default() -> Self {
+		return Self {
+			regex: true,
+			smart_case: true,
+			whole_word: false,
+			wrap: true,
+		};
+	}
+}
+
+/// the matches of a query over the whole content, kept until the content or the query changes
+pub(crate) struct MatchCache {
+	pattern: String,
+	opts: SearchOpts,
+	revision: u64,
+	matches: Vec<Range>,
+}
+
+/// a compiled search pattern
+#[derive(Clone, Debug)]
+pub struct Query {
+	pub pattern: String,
+	pub opts: SearchOpts,
+	re: Regex,
+}
+
+impl Query {
+
+	pub fn new(pattern: &str, opts: SearchOpts) -> Result<Self, regex::Error> {
+
+		let mut src = if opts.regex {
+			String::from(pattern)
+		} else {
+			regex::escape(pattern)
+		};
+
+		if opts.whole_word {
+			src = format!(r"\b(?:{})\b", src);
+		}
+
+		let ignore_case = opts.smart_case && !pattern.chars().any(char::is_uppercase);
+		let re = RegexBuilder::new(&src)
+			.case_insensitive(ignore_case)
+			.build()?;
+
+		return Ok(Self {
+			pattern: String::from(pattern),
+			opts: opts,
+			re: re,
+		});
+
+	}
+
+	/// get the compiled regex
+	pub fn regex(&self) -> &Regex {
+		return &self.re;
+	}
+
+	/// get the byte ranges of every non empty match in a line
+	pub fn find_in(&self, line: &str) -> Vec<(usize, usize)> {
+		return self.re
+			.find_iter(line)
+			.filter(|m| m.start() < m.end())
+			.map(|m| (m.start(), m.end()))
+			.collect();
+	}
+
+}
+
+impl Buffer {
+
+	/// get the matches of a line as ranges, the end is inclusive
+	fn line_matches(&self, query: &Query, ln: Line) -> Vec<Range> {
+
+		let line = match self.get_line_at(ln) {
+			Some(line) => line,
+			None => return vec![],
+		};
+
+		return query
+			.find_in(&line)
+			.into_iter()
+			.map(|(start, end)| Range {
+				start: Pos::new(ln, byte_to_col(&line, start)),
+				end: Pos::new(ln, byte_to_col(&line, end - 1)),
+			})
+			.collect();
+
+	}
+
+	/// get every match between two lines (inclusive), for highlighting a view
+	pub fn search_matches(&self, query: &Query, start: Line, end: Line) -> Vec<Range> {
+
+		let end = cmp::min(end, self.line_count() as Line);
+
+		return (cmp::max(start, 1)..=end)
+			.flat_map(|ln| self.line_matches(query, ln))
+			.collect();
+
+	}
+
+	/// get every match in the buffer
+	pub fn search_all(&self, query: &Query) -> Vec<Range> {
+		return self.search_matches(query, 1, self.line_count() as Line);
+	}
+
+	/// find the first match starting after a position
+	pub fn find_next_at(&self, query: &Query, pos: Pos) -> Option<Range> {
+
+		let count = self.line_count() as Line;
+
+		if let Some(r) = self.line_matches(query, pos.line).into_iter().find(|r| r.start.col > pos.col) {
+			return Some(r);
+		}
+
+		for ln in pos.line + 1..=count {
+			if let Some(r) = self.line_matches(query, ln).into_iter().next() {
+				return Some(r);
+			}
+		}
+
+		if query.opts.wrap {
+			for ln in 1..=pos.line {
+				if let Some(r) = self.line_matches(query, ln).into_iter().next() {
+					return Some(r);
+				}
+			}
+		}
+
+		return None;
+
+	}
+
+	/// find the last match starting before a position
+	pub fn find_prev_at(&self, query: &Query, pos: Pos) -> Option<Range> {
+
+		let count = self.line_count() as Line;
+
+		if let Some(r) = self.line_matches(query, pos.line).into_iter().rev().find(|r| r.start.col < pos.col) {
+			return Some(r);
+		}
+
+		for ln in (1..pos.line).rev() {
+			if let Some(r) = self.line_matches(query, ln).into_iter().last() {
+				return Some(r);
+			}
+		}
+
+		if query.opts.wrap {
+			for ln in (pos.line..=count).rev() {
+				if let Some(r) = self.line_matches(query, ln).into_iter().last() {
+					return Some(r);
+				}
+			}
+		}
+
+		return None;
+
+	}
+
+	/// find the next match from the cursor
+	pub fn find_next(&self, query: &Query) -> Option<Range> {
+		return self.find_next_at(query, self.cursor);
+	}
+
+	/// find the prev match from the cursor
+	pub fn find_prev(&self, query: &Query) -> Option<Range> {
+		return self.find_prev_at(query, self.cursor);
+	}
+
+	/// get which match starts at a position and the total number of matches, as in "match 3 of 17", the matches are kept until the content or the query changes
+	pub fn match_index_at(&self, query: &Query, pos: Pos) -> Option<(usize, usize)> {
+
+		let mut cache = self.match_cache.borrow_mut();
+		let fresh = cache
+			.as_ref()
+			.map(|c| c.revision == self.revision && c.pattern == query.pattern && c.opts == query.opts)
+			.unwrap_or(false);
+
+		if !fresh {
+			*cache = Some(MatchCache {
+				pattern: query.pattern.clone(),
+				opts: query.opts.clone(),
+				revision: self.revision,
+				matches: self.search_all(query),
+			});
+		}
+
+		let matches = &cache.as_ref()?.matches;
+		let i = matches.binary_search_by(|r| r.start.cmp(&pos)).ok()?;
+
+		return Some((i + 1, matches.len()));
+
+	}
+
+	/// move every cursor to the next match
+	pub fn goto_next_match(&mut self, query: &Query) {
+		self.move_each(|buf, pos| buf.find_next_at(query, pos).map(|r| r.start).unwrap_or(pos));
+	}
+
+	/// move every cursor to the prev match
+	pub fn goto_prev_match(&mut self, query: &Query) {
+		self.move_each(|buf, pos| buf.find_prev_at(query, pos).map(|r| r.start).unwrap_or(pos));
+	}
+
+	/// get the query being typed in search mode
+	pub fn search_text(&self) -> Option<&str> {
+		if let Mode::Search { text, .. } = &self.mode {
+			return Some(text);
+		}
+		return None;
+	}
+
+	/// get the last confirmed search
+	pub fn last_search(&self) -> Option<&Query> {
+		return self.last_search.as_ref();
+	}
+
+	/// get the confirmed search queries, oldest first
+	pub fn search_history(&self) -> &[String] {
+		return &self.search_history;
+	}
+
+	/// get the query to highlight, the one being typed or the last confirmed one
+	pub fn active_query(&self) -> Option<Query> {
+
+		if let Some(text) = self.search_text() {
+			if text.is_empty() {
+				return None;
+			}
+			return Query::new(text, self.search_opts.clone()).ok();
+		}
+
+		return self.last_search.clone();
+
+	}
+
+	/// replace the query being typed and preview the nearest match
+	pub fn set_search_text(&mut self, s: &str) {
+
+		if let Mode::Search { text, history, .. } = &mut self.mode {
+			*text = String::from(s);
+			*history = None;
+		}
+
+		self.search_preview();
+
+	}
+
+	/// type a char into the search query
+	pub fn search_input(&mut self, ch: char) {
+
+		if !is_printable(ch) {
+			return;
+		}
+
+		if let Some(mut text) = self.search_text().map(String::from) {
+			text.push(ch);
+			self.set_search_text(&text);
+		}
+
+	}
+
+	/// delete the last char of the search query
+	pub fn search_del(&mut self) {
+
+		if let Some(mut text) = self.search_text().map(String::from) {
+			text.pop();
+			self.set_search_text(&text);
+		}
+
+	}
+
+	/// move the cursor to the first match from where the search started
+	fn search_preview(&mut self) {
+
+		let origin = match self.mode {
+			Mode::Search { origin, .. } => origin,
+			_ => return,
+		};
+
+		let found = self.active_query().and_then(|q| self.find_next_at(&q, Pos {
+			col: origin.col - 1,
+			.. origin
+		}));
+
+		match found {
+			Some(r) => self.move_to(r.start),
+			None => self.move_to(origin),
+		}
+
+	}
+
+	/// step to an older query in the search history
+	pub fn search_history_prev(&mut self) {
+
+		let len = self.search_history.len();
+
+		if let Mode::Search { text, history, .. } = &mut self.mode {
+
+			let i = match *history {
+				Some(i) => i.saturating_sub(1),
+				None if len > 0 => len - 1,
+				None => return,
+			};
+
+			*text = self.search_history[i].clone();
+			*history = Some(i);
+
+		}
+
+		self.search_preview();
+
+	}
+
+	/// step to a newer query in the search history, past the newest one clears the query
+	pub fn search_history_next(&mut self) {
+
+		let len = self.search_history.len();
+
+		if let Mode::Search { text, history, .. } = &mut self.mode {
+
+			match *history {
+				Some(i) if i + 1 < len => {
+					*text = self.search_history[i + 1].clone();
+					*history = Some(i + 1);
+				},
+				Some(_) => {
+					text.clear();
+					*history = None;
+				},
+				None => return,
+			}
+
+		}
+
+		self.search_preview();
+
+	}
+
+	/// accept the search, keeping the cursor at the match
+	pub fn search_confirm(&mut self) {
+
+		let text = match self.search_text() {
+			Some(text) => String::from(text),
+			None => return,
+		};
+
+		if let Mode::Search { origin, .. } = self.mode {
+			if origin != self.cursor {
+				self.push_jump(origin);
+			}
+		}
+
+		if !text.is_empty() {
+
+			self.search_history.retain(|t| t != &text);
+			self.search_history.push(text.clone());
+
+			if let Ok(query) = Query::new(&text, self.search_opts.clone()) {
+				self.last_search = Some(query);
+			}
+
+		}
+
+		self.mode = Mode::Normal;
+		self.adjust_cursor();
+
+	}
+
+	/// abort the search, going back to where it started
+	pub fn search_cancel(&mut self) {
+
+		if let Mode::Search { origin, .. } = self.mode {
+			self.mode = Mode::Normal;
+			self.move_to(origin);
+		}
+
+	}
+
+	/// repeat the last search forward or backward from every cursor
+	pub fn search_repeat(&mut self, forward: bool) {
+
+		if let Some(query) = self.last_search.clone() {
+			if forward {
+				self.goto_next_match(&query);
+			} else {
+				self.goto_prev_match(&query);
+			}
+		}
+
+	}
+
+}
+
@@ -0,0 +1,272 @@
+// wengwengweng
+
+use std::cmp;
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SelectKind {
+	Char,
+	Line,
+	Block,
+}
+
+impl Buffer {
+
+	/// start selecting from the current cursor, or switch the kind of the current selection
+	pub fn start_select(&mut self, kind: SelectKind) {
+
+		match self.mode {
+
+			Mode::Select { kind: cur_kind, anchor } => {
+				if cur_kind == kind {
+					self.end_select();
+				} else {
+					self.mode = Mode::Select {
+						kind: kind,
+						anchor: anchor,
+					};
+				}
+			},
+
+			_ => {
+				self.mode = Mode::Select {
+					kind: kind,
+					anchor: self.cursor,
+				};
+			},
+
+		}
+
+	}
+
+	/// leave select mode without moving the cursor, remembering the lines for '< and '>
+	pub fn end_select(&mut self) {
+		if let Mode::Select { .. } = self.mode {
+			let ranges = self.selections();
+			if let (Some(first), Some(last)) = (ranges.first(), ranges.last()) {
+				self.set_mark_at('<', first.start);
+				self.set_mark_at('>', last.end);
+			}
+			self.mode = Mode::Normal;
+			self.adjust_cursor();
+		}
+	}
+
+	/// get the kind of the current selection
+	pub fn select_kind(&self) -> Option<SelectKind> {
+		if let Mode::Select { kind, .. } = self.mode {
+			return Some(kind);
+		}
+		return None;
+	}
+
+	/// get the selected ranges, block selections give one range per line
+	pub fn selections(&self) -> Vec<Range> {
+
+		let (kind, anchor) = match self.mode {
+			Mode::Select { kind, anchor } => (kind, anchor),
+			_ => return vec![],
+		};
+
+		let start = cmp::min(anchor, self.cursor);
+		let end = cmp::max(anchor, self.cursor);
+
+		return match kind {
+
+			SelectKind::Char => vec![Range {
+				start: start,
+				end: end,
+			}],
+
+			SelectKind::Line => vec![Range {
+				start: Pos::new(start.line, 1),
+				end: Pos::new(end.line, self.line_len(end.line)),
+			}],
+
+			SelectKind::Block => {
+
+				// the same tab width the layout draws with
+				let tw = self.layout.tab_width;
+				let a = self.get_shifted_pos(anchor, tw);
+				let b = self.get_shifted_pos(self.cursor, tw);
+				let left = cmp::min(a, b);
+				let right = cmp::max(a, b);
+
+				(start.line..=end.line)
+					.filter_map(|ln| {
+
+						let len = self.line_len(ln);
+						let start_col = self.get_unshifted_col(left, ln, tw);
+
+						if start_col > len {
+							return None;
+						}
+
+						let end_col = clamp(self.get_unshifted_col(right, ln, tw), start_col, len);
+
+						return Some(Range {
+							start: Pos::new(ln, start_col),
+							end: Pos::new(ln, end_col),
+						});
+
+					})
+					.collect()
+
+			},
+
+		};
+
+	}
+
+	/// get the lines covered by the selection
+	pub fn selected_lines(&self) -> Option<(Line, Line)> {
+
+		let ranges = self.selections();
+		let first = ranges.first()?;
+		let last = ranges.last()?;
+
+		return Some((first.start.line, last.end.line));
+
+	}
+
+	/// get the selected text, block selections are joined by lines
+	pub fn selected_text(&self) -> Option<String> {
+
+		let ranges = self.selections();
+
+		if ranges.is_empty() {
+			return None;
+		}
+
+		let texts: Vec<String> = ranges
+			.iter()
+			.filter_map(|r| self.get_range(*r))
+			.collect();
+
+		let mut text = texts.join("\n");
+
+		if self.select_kind() == Some(SelectKind::Line) {
+			text.push('\n');
+		}
+
+		return Some(text);
+
+	}
+
+	/// delete the selection
+	pub fn select_delete(&mut self) {
+
+		let ranges = self.selections();
+		let (start, end) = match self.selected_lines() {
+			Some(lines) => lines,
+			None => return,
+		};
+
+		self.yank_selected();
+		self.push_undo();
+		self.begin_undo_group();
+
+		let cursor = if self.select_kind() == Some(SelectKind::Line) {
+
+			for ln in (start..=end).rev() {
+				self.del_line_at(ln);
+			}
+
+			Pos::new(start, 1)
+
+		} else {
+
+			for r in ranges.iter().rev() {
+				self.del_range(*r);
+			}
+
+			ranges[0].start
+
+		};
+
+		self.end_undo_group();
+		self.end_select();
+		self.move_to(cursor);
+
+	}
+
+	/// copy the selected text to the register in use
+	fn yank_selected(&mut self) {
+
+		let mut text = match self.selected_text() {
+			Some(text) => text,
+			None => return,
+		};
+
+		if self.select_kind() == Some(SelectKind::Line) {
+			text.pop();
+			self.yank_text(&text, RegisterKind::Line);
+		} else {
+			self.yank_text(&text, RegisterKind::Char);
+		}
+
+	}
+
+	/// copy the selection
+	pub fn select_yank(&mut self) {
+
+		let start = match self.selections().first() {
+			Some(r) => r.start,
+			None => return,
+		};
+
+		self.yank_selected();
+		self.end_select();
+		self.move_to(start);
+
+	}
+
+	/// replace every selected range with text
+	pub fn select_replace(&mut self, text: &str) {
+
+		let ranges = self.selections();
+
+		if ranges.is_empty() {
+			return;
+		}
+
+		self.push_undo();
+		self.begin_undo_group();
+
+		for r in ranges.iter().rev() {
+			self.replace_range(*r, text);
+		}
+
+		self.end_undo_group();
+		self.end_select();
+		self.move_to(ranges[0].start);
+
+	}
+
+	/// run a line edit on every selected line as one undo step
+	fn select_each_line<F: FnMut(&mut Self, Line)>(&mut self, f: F) {
+		if let Some((start, end)) = self.selected_lines() {
+			self.edit_lines(start, end, f);
+		}
+	}
+
+	/// indent the selected lines forward
+	pub fn select_indent_forward(&mut self) {
+		self.select_each_line(|buf, ln| buf.indent_forward_at(ln));
+	}
+
+	/// indent the selected lines backward
+	pub fn select_indent_backward(&mut self) {
+		self.select_each_line(|buf, ln| buf.indent_backward_at(ln));
+	}
+
+	/// comment the selected lines, or uncomment them if they're all commented
+	pub fn select_toggle_comment(&mut self) {
+		if let Some((start, end)) = self.selected_lines() {
+			self.toggle_comment_lines(start, end);
+		}
+	}
+
+}
+
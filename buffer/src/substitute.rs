@@ -0,0 +1,239 @@
+// wengwengweng
+
+use super::*;
+
+/// which part of the buffer a substitution works on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubScope {
+	/// the line of the main cursor
+	Line,
+	/// lines between two lines (inclusive)
+	Lines(Line, Line),
+	/// the current selection
+	Selection,
+	/// the whole file
+	File,
+}
+
+/// one replacement proposed by a substitution
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubEdit {
+	pub at: Pos,
+	pub deleted: String,
+	pub inserted: String,
+}
+
+/// a substitution going through its matches one by one, for confirming each
+#[derive(Clone, Debug)]
+pub struct Substitution {
+	edits: Vec<SubEdit>,
+	accepted: Vec<bool>,
+	current: usize,
+}
+
+impl Substitution {
+
+	/// get the match waiting to be confirmed
+	pub fn current(&self) -> Option<&SubEdit> {
+		return self.edits.get(self.current);
+	}
+
+	/// replace the current match and go to the next one
+	pub fn accept(&mut self) {
+		if self.current < self.edits.len() {
+			self.accepted[self.current] = true;
+			self.current += 1;
+		}
+	}
+
+	/// keep the current match and go to the next one
+	pub fn skip(&mut self) {
+		if self.current < self.edits.len() {
+			self.current += 1;
+		}
+	}
+
+	/// replace the current match and every one after it
+	pub fn accept_rest(&mut self) {
+		while !self.is_done() {
+			self.accept();
+		}
+	}
+
+	/// stop confirming, the remaining matches are kept
+	pub fn quit(&mut self) {
+		self.current = self.edits.len();
+	}
+
+	/// only keep the matches a predicate returns true for
+	pub fn retain<F: FnMut(&SubEdit) -> bool>(&mut self, f: F) {
+		self.edits.retain(f);
+		self.accepted = vec![false; self.edits.len()];
+		self.current = 0;
+	}
+
+	/// if every match has been confirmed or skipped
+	pub fn is_done(&self) -> bool {
+		return self.current >= self.edits.len();
+	}
+
+	/// get the edits that were accepted
+	pub fn accepted(&self) -> Vec<SubEdit> {
+		return self.edits
+			.iter()
+			.zip(&self.accepted)
+			.filter(|(_, a)| **a)
+			.map(|(e, _)| e.clone())
+			.collect();
+	}
+
+}
+
+impl Buffer {
+
+	/// get the lines and byte windows a scope covers
+	fn sub_windows(&self, scope: SubScope) -> Vec<(Line, usize, usize)> {
+
+		let whole = |ln: Line| {
+			return self.get_line_at(ln).map(|l| (ln, 0, l.len()));
+		};
+
+		return match scope {
+
+			SubScope::Line => whole(self.cursor.line).into_iter().collect(),
+			SubScope::Lines(start, end) => (start..=end).filter_map(whole).collect(),
+			SubScope::File => (1..=self.line_count() as Line).filter_map(whole).collect(),
+
+			SubScope::Selection => {
+
+				let mut windows = vec![];
+
+				for r in self.selections() {
+
+					for ln in r.start.line..=r.end.line {
+
+						if let Some(line) = self.get_line_at(ln) {
+
+							let lo = if ln == r.start.line {
+								col_to_byte(&line, r.start.col)
+							} else {
+								0
+							};
+
+							let hi = if ln == r.end.line {
+								col_to_byte(&line, r.end.col + 1)
+							} else {
+								line.len()
+							};
+
+							windows.push((ln, lo, hi));
+
+						}
+
+					}
+
+				}
+
+				windows
+
+			},
+
+		};
+
+	}
+
+	/// get the replacements a substitution would make without changing anything, the template can refer to captures with $1 or ${name}
+	pub fn substitute_preview(&self, query: &Query, template: &str, scope: SubScope) -> Vec<SubEdit> {
+
+		let mut edits = vec![];
+
+		for (ln, lo, hi) in self.sub_windows(scope) {
+
+			let line = match self.get_line_at(ln) {
+				Some(line) => line,
+				None => continue,
+			};
+
+			for caps in query.regex().captures_iter(&line) {
+
+				let m = match caps.get(0) {
+					Some(m) => m,
+					None => continue,
+				};
+
+				if m.start() < lo || m.end() > hi {
+					continue;
+				}
+
+				let mut inserted = String::new();
+
+				caps.expand(template, &mut inserted);
+
+				edits.push(SubEdit {
+					at: Pos::new(ln, byte_to_col(&line, m.start())),
+					deleted: String::from(m.as_str()),
+					inserted: inserted,
+				});
+
+			}
+
+		}
+
+		return edits;
+
+	}
+
+	/// start a substitution that confirms every match
+	pub fn substitute_interactive(&self, query: &Query, template: &str, scope: SubScope) -> Substitution {
+
+		let edits = self.substitute_preview(query, template, scope);
+		let len = edits.len();
+
+		return Substitution {
+			edits: edits,
+			accepted: vec![false; len],
+			current: 0,
+		};
+
+	}
+
+	/// apply replacements as one undo step, returns how many were made
+	pub fn apply_sub_edits(&mut self, edits: &[SubEdit]) -> usize {
+
+		if edits.is_empty() {
+			return 0;
+		}
+
+		self.push_undo();
+		self.begin_undo_group();
+
+		let mut count = 0;
+
+		for e in edits.iter().rev() {
+			if let Some(start) = self.pos_to_char(e.at) {
+				self.edit_chars(start, start + e.deleted.chars().count(), &e.inserted);
+				count += 1;
+			}
+		}
+
+		self.end_undo_group();
+		self.end_select();
+		self.move_to(Pos::new(edits[0].at.line, 1));
+
+		return count;
+
+	}
+
+	/// apply the accepted matches of an interactive substitution
+	pub fn apply_substitution(&mut self, sub: &Substitution) -> usize {
+		return self.apply_sub_edits(&sub.accepted());
+	}
+
+	/// replace every match in a scope, as one undo step
+	pub fn substitute(&mut self, query: &Query, template: &str, scope: SubScope) -> usize {
+		let edits = self.substitute_preview(query, template, scope);
+		return self.apply_sub_edits(&edits);
+	}
+
+}
+
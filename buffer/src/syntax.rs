@@ -1,37 +1,96 @@
 // wengwengweng
 
+use std::rc::Rc;
+
+use pest::error::ErrorVariant;
+use pest::error::LineColLocation;
 use pest_vm::Vm;
 use pest_meta::parser;
 use pest_meta::optimizer;
 
 use super::*;
 
+#[derive(Clone)]
 pub struct Syntax {
-	vm: Option<Vm>,
+	vm: Option<Rc<Vm>>,
 }
 
-impl Syntax {
+/// why a grammar failed to compile
+#[derive(Clone, Debug)]
+pub struct SyntaxError {
+	pub line: usize,
+	pub msg: String,
+}
 
-	pub fn new(code: &str) -> Self {
+/// rule names joined like "a, b or c"
+fn rule_list(rules: &[parser::Rule]) -> String {
+
+	let names: Vec<String> = rules
+		.iter()
+		.map(|r| format!("{:?}", r))
+		.collect();
+
+	return match names.split_last() {
+		Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+		Some((last, _)) => last.clone(),
+		None => String::new(),
+	};
+
+}
+
+impl SyntaxError {
+	fn from_pest(e: &pest::error::Error<parser::Rule>) -> Self {
 
-		let mut vm = None;
+		let line = match e.line_col {
+			LineColLocation::Pos((line, _)) => line,
+			LineColLocation::Span((line, _), _) => line,
+		};
 
-		match parser::parse(parser::Rule::grammar_rules, code) {
-			Ok(pairs) => {
-				if let Ok(ast) = parser::consume_rules(pairs) {
-					vm = Some(Vm::new(optimizer::optimize(ast.clone())));
+		let msg = match &e.variant {
+			ErrorVariant::CustomError { message } => message.clone(),
+			ErrorVariant::ParsingError { positives, negatives } => {
+				match (negatives.is_empty(), positives.is_empty()) {
+					(false, false) => format!("unexpected {}; expected {}", rule_list(negatives), rule_list(positives)),
+					(false, true) => format!("unexpected {}", rule_list(negatives)),
+					(true, false) => format!("expected {}", rule_list(positives)),
+					(true, true) => String::from("unknown parsing error"),
 				}
 			},
-			Err(e) => {
-				eprintln!("syntax file error: {:?}", e);
-			},
-		}
+		};
 
 		return Self {
-			vm: vm,
+			line: line,
+			msg: msg,
 		};
 
 	}
+}
+
+impl Syntax {
+
+	pub fn new(code: &str) -> Self {
+		return match Self::compile(code) {
+			Ok(syntax) => syntax,
+			Err(e) => {
+				eprintln!("syntax file error: {}: {}", e.line, e.msg);
+				Self::none()
+			},
+		};
+	}
+
+	/// compile a pest grammar with a "line" rule
+	pub fn compile(code: &str) -> Result<Self, SyntaxError> {
+
+		let pairs = parser::parse(parser::Rule::grammar_rules, code)
+			.map_err(|e| SyntaxError::from_pest(&e))?;
+		let ast = parser::consume_rules(pairs)
+			.map_err(|errs| SyntaxError::from_pest(&errs[0]))?;
+
+		return Ok(Self {
+			vm: Some(Rc::new(Vm::new(optimizer::optimize(ast)))),
+		});
+
+	}
 
 	pub fn none() -> Self {
 		return Self {
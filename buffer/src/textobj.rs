@@ -0,0 +1,303 @@
+// wengwengweng
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::*;
+
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
+	/// letters and digits, or a run of punctuation
+	Word,
+	/// anything but whitespace
+	BigWord,
+	/// a pair from the filetype by either of its chars, or a quote
+	Pair(char),
+	/// lines between blank lines
+	Paragraph,
+	/// lines with at least the indent of the current line
+	Indent,
+}
+
+impl TextObject {
+
+	/// get the text object for the key after i / a, like w in "diw"
+	pub fn from_char(ch: char) -> Self {
+		return match ch {
+			'w' => TextObject::Word,
+			'W' => TextObject::BigWord,
+			'p' => TextObject::Paragraph,
+			'i' | 'I' => TextObject::Indent,
+			'b' => TextObject::Pair('('),
+			'B' => TextObject::Pair('{'),
+			c => TextObject::Pair(c),
+		};
+	}
+
+	/// if the object covers whole lines
+	pub fn is_linewise(&self) -> bool {
+		return match self {
+			TextObject::Paragraph | TextObject::Indent => true,
+			_ => false,
+		};
+	}
+
+}
+
+impl Buffer {
+
+	/// get the open and close char of a pair by either of its chars
+	pub fn pair_of(&self, ch: char) -> Option<(char, char)> {
+
+		if let Some(close) = self.filetype.pairs.get(&ch) {
+			return Some((ch, *close));
+		}
+
+		if let Some((open, _)) = self.filetype.pairs.iter().find(|(_, close)| **close == ch) {
+			return Some((*open, ch));
+		}
+
+		if QUOTES.contains(&ch) {
+			return Some((ch, ch));
+		}
+
+		return None;
+
+	}
+
+	/// get the range of a text object around a position, inner leaves out the surrounding pair or whitespace
+	pub fn text_object_at(&self, pos: Pos, obj: TextObject, inner: bool) -> Option<Range> {
+		return match obj {
+			TextObject::Word => self.word_object_at(pos, inner, false),
+			TextObject::BigWord => self.word_object_at(pos, inner, true),
+			TextObject::Pair(ch) => {
+				let (open, close) = self.pair_of(ch)?;
+				if open == close {
+					self.quote_object_at(pos, open, inner)
+				} else {
+					self.pair_object_at(pos, open, close, inner)
+				}
+			},
+			TextObject::Paragraph => self.paragraph_object_at(pos, inner),
+			TextObject::Indent => self.indent_object_at(pos, inner),
+		};
+	}
+
+	/// get the range of a text object around the cursor
+	pub fn text_object(&self, obj: TextObject, inner: bool) -> Option<Range> {
+		return self.text_object_at(self.cursor, obj, inner);
+	}
+
+	fn word_object_at(&self, pos: Pos, inner: bool, big: bool) -> Option<Range> {
+
+		let line = self.get_line_at(pos.line)?;
+		let graphemes: Vec<&str> = line.graphemes(true).collect();
+		let class = |g: &str| {
+			if g.trim().is_empty() {
+				return 0;
+			} else if big || self.is_word(g) {
+				return 1;
+			} else {
+				return 2;
+			}
+		};
+
+		let i = pos.col as usize - 1;
+		let c = class(graphemes.get(i)?);
+		let mut start = i;
+		let mut end = i;
+
+		while start > 0 && class(graphemes[start - 1]) == c {
+			start -= 1;
+		}
+
+		while end + 1 < graphemes.len() && class(graphemes[end + 1]) == c {
+			end += 1;
+		}
+
+		if !inner {
+
+			let mut trail = end;
+
+			while trail + 1 < graphemes.len() && class(graphemes[trail + 1]) == 0 && c != 0 {
+				trail += 1;
+			}
+
+			if trail > end {
+				end = trail;
+			} else {
+				while start > 0 && class(graphemes[start - 1]) == 0 && c != 0 {
+					start -= 1;
+				}
+			}
+
+		}
+
+		return Some(Range {
+			start: Pos::new(pos.line, start as Col + 1),
+			end: Pos::new(pos.line, end as Col + 1),
+		});
+
+	}
+
+	fn pair_object_at(&self, pos: Pos, open: char, close: char, inner: bool) -> Option<Range> {
+
+		let start = self.find_open_at(pos, open, close)?;
+		let end = self.find_close_at(start, open, close)?;
+		let (start, end) = (self.pos_to_char(start)?, self.pos_to_char(end)?);
+
+		if inner {
+
+			if end <= start + 1 {
+				return None;
+			}
+
+			return Some(Range {
+				start: self.char_to_pos(start + 1),
+				end: self.char_to_pos(end - 1),
+			});
+
+		}
+
+		return Some(Range {
+			start: self.char_to_pos(start),
+			end: self.char_to_pos(end),
+		});
+
+	}
+
+	fn quote_object_at(&self, pos: Pos, quote: char, inner: bool) -> Option<Range> {
+
+		let line = self.get_line_at(pos.line)?;
+		let chars: Vec<char> = line.chars().collect();
+		let quotes: Vec<usize> = chars
+			.iter()
+			.enumerate()
+			.filter(|(i, c)| **c == quote && (*i == 0 || chars[i - 1] != '\\'))
+			.map(|(i, _)| i)
+			.collect();
+
+		let idx = self.pos_to_char(pos)? - self.line_to_char(pos.line)?;
+
+		// the pair around the cursor, or the first one after it
+		let (start, end) = quotes
+			.chunks(2)
+			.filter(|p| p.len() == 2)
+			.map(|p| (p[0], p[1]))
+			.find(|(_, end)| idx <= *end)?;
+
+		let line_start = self.line_to_char(pos.line)?;
+
+		if inner {
+
+			if end <= start + 1 {
+				return None;
+			}
+
+			return Some(Range {
+				start: self.char_to_pos(line_start + start + 1),
+				end: self.char_to_pos(line_start + end - 1),
+			});
+
+		}
+
+		return Some(Range {
+			start: self.char_to_pos(line_start + start),
+			end: self.char_to_pos(line_start + end),
+		});
+
+	}
+
+	fn is_blank_at(&self, ln: Line) -> bool {
+		return self.get_line_at(ln).map(|l| l.trim().is_empty()).unwrap_or(false);
+	}
+
+	/// get a range over whole lines
+	fn line_range(&self, start: Line, end: Line) -> Range {
+		return Range {
+			start: Pos::new(start, 1),
+			end: Pos::new(end, self.line_len(end)),
+		};
+	}
+
+	fn paragraph_object_at(&self, pos: Pos, inner: bool) -> Option<Range> {
+
+		let last = self.line_count() as Line;
+		let blank = self.is_blank_at(pos.line);
+		let mut start = pos.line;
+		let mut end = pos.line;
+
+		while start > 1 && self.is_blank_at(start - 1) == blank {
+			start -= 1;
+		}
+
+		while end < last && self.is_blank_at(end + 1) == blank {
+			end += 1;
+		}
+
+		if !inner {
+
+			let mut trail = end;
+
+			while trail < last && self.is_blank_at(trail + 1) != blank {
+				trail += 1;
+			}
+
+			if trail > end {
+				end = trail;
+			} else if !blank {
+				while start > 1 && self.is_blank_at(start - 1) {
+					start -= 1;
+				}
+			}
+
+		}
+
+		return Some(self.line_range(start, end));
+
+	}
+
+	fn indent_object_at(&self, pos: Pos, inner: bool) -> Option<Range> {
+
+		let last = self.line_count() as Line;
+		let level = self.get_indent_at(pos.line)?;
+		let inside = |ln: Line| {
+			return self.is_blank_at(ln) || self.get_indent_at(ln).map(|i| i >= level).unwrap_or(false);
+		};
+
+		let mut start = pos.line;
+		let mut end = pos.line;
+
+		while start > 1 && inside(start - 1) {
+			start -= 1;
+		}
+
+		while end < last && inside(end + 1) {
+			end += 1;
+		}
+
+		// blank lines at the edges don't belong to the block
+		while start < pos.line && self.is_blank_at(start) {
+			start += 1;
+		}
+
+		while end > pos.line && self.is_blank_at(end) {
+			end -= 1;
+		}
+
+		if !inner {
+			if start > 1 {
+				start -= 1;
+			}
+			if end < last && !self.is_blank_at(end + 1) {
+				end += 1;
+			}
+		}
+
+		return Some(self.line_range(start, end));
+
+	}
+
+}
+
@@ -0,0 +1,434 @@
+// wengwengweng
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde::Deserialize;
+
+use super::*;
+
+/// a single change to the content, positions are char indices
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edit {
+	pub at: usize,
+	pub deleted: String,
+	pub inserted: String,
+}
+
+impl Edit {
+
+	pub fn new(at: usize, deleted: &str, inserted: &str) -> Self {
+		return Self {
+			at: at,
+			deleted: String::from(deleted),
+			inserted: String::from(inserted),
+		};
+	}
+
+	/// get the edit that reverts this one
+	pub fn invert(&self) -> Self {
+		return Self {
+			at: self.at,
+			deleted: self.inserted.clone(),
+			inserted: self.deleted.clone(),
+		};
+	}
+
+	/// number of chars removed
+	pub fn deleted_len(&self) -> usize {
+		return self.deleted.chars().count();
+	}
+
+	/// number of chars added
+	pub fn inserted_len(&self) -> usize {
+		return self.inserted.chars().count();
+	}
+
+	/// try to merge a following edit into this one, for typing and backspacing
+	fn merge(&mut self, next: &Edit) -> bool {
+
+		if !self.deleted.is_empty() || !next.deleted.is_empty() {
+
+			// backspacing
+			if self.inserted.is_empty() && next.inserted.is_empty() && next.at + next.deleted_len() == self.at {
+				self.deleted.insert_str(0, &next.deleted);
+				self.at = next.at;
+				return true;
+			}
+
+			return false;
+
+		}
+
+		// typing
+		if next.at == self.at + self.inserted_len() {
+			self.inserted.push_str(&next.inserted);
+			return true;
+		}
+
+		return false;
+
+	}
+
+}
+
+/// a group of edits that are undone and redone as one step
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+	pub edits: Vec<Edit>,
+	pub cursor_before: Pos,
+	pub cursor_after: Pos,
+}
+
+/// edits to apply in order to get to another state, and where the cursor goes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+	pub edits: Vec<Edit>,
+	pub cursor: Pos,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Node {
+	parent: usize,
+	children: Vec<usize>,
+	redo_child: Option<usize>,
+	transaction: Transaction,
+}
+
+/// the undo history as stored on disk
+#[derive(Serialize, Deserialize)]
+struct UndoFile {
+	path: PathBuf,
+	hash: u64,
+	saved: usize,
+	nodes: Vec<Node>,
+}
+
+/// where the undo history of a file is kept in the user data dir
+pub fn undo_file_path(path: &Path) -> Option<PathBuf> {
+
+	let dir = dirs::data_dir()?
+		.join("hop")
+		.join("undo");
+
+	return Some(dir.join(format!("{:016x}.json", utils::hash_path(path))));
+
+}
+
+/// if the links between loaded nodes are sound, every id exists and parents come before their children
+fn valid_nodes(nodes: &[Node]) -> bool {
+
+	if nodes.is_empty() {
+		return false;
+	}
+
+	for (id, node) in nodes.iter().enumerate() {
+
+		if id > 0 && (node.parent >= id || !nodes[node.parent].children.contains(&id)) {
+			return false;
+		}
+
+		if node.children.iter().any(|c| *c >= nodes.len() || *c == 0 || nodes[*c].parent != id) {
+			return false;
+		}
+
+		if let Some(c) = node.redo_child {
+			if !node.children.contains(&c) {
+				return false;
+			}
+		}
+
+	}
+
+	return true;
+
+}
+
+/// undo history kept as a tree, editing after an undo starts a new branch instead of dropping the redo states
+///
+/// node ids are given in creation order, so they also work as a timeline for earlier() / later()
+pub struct UndoTree {
+	nodes: Vec<Node>,
+	current: usize,
+	saved: usize,
+	pending: Option<Transaction>,
+}
+
+impl Default for UndoTree {
+	fn default() -> Self {
+		return Self::new();
+	}
+}
+
+impl UndoTree {
+
+	pub fn new() -> Self {
+
+		let root = Node {
+			parent: 0,
+			children: Vec::new(),
+			redo_child: None,
+			transaction: Transaction {
+				edits: Vec::new(),
+				cursor_before: Pos::new(1, 1),
+				cursor_after: Pos::new(1, 1),
+			},
+		};
+
+		return Self {
+			nodes: vec![root],
+			current: 0,
+			saved: 0,
+			pending: None,
+		};
+
+	}
+
+	/// record an edit into the pending transaction
+	pub fn record(&mut self, edit: Edit, cursor: Pos) {
+
+		let pending = self.pending.get_or_insert_with(|| Transaction {
+			edits: Vec::new(),
+			cursor_before: cursor,
+			cursor_after: cursor,
+		});
+
+		if let Some(last) = pending.edits.last_mut() {
+			if last.merge(&edit) {
+				return;
+			}
+		}
+
+		pending.edits.push(edit);
+
+	}
+
+	/// if there are edits not yet committed
+	pub fn has_pending(&self) -> bool {
+		return self.pending.is_some();
+	}
+
+	/// close the pending transaction as one undo step
+	pub fn commit(&mut self, cursor: Pos) {
+
+		if let Some(mut transaction) = self.pending.take() {
+
+			if transaction.edits.is_empty() {
+				return;
+			}
+
+			transaction.cursor_after = cursor;
+
+			let id = self.nodes.len();
+
+			self.nodes.push(Node {
+				parent: self.current,
+				children: Vec::new(),
+				redo_child: None,
+				transaction: transaction,
+			});
+
+			let parent = &mut self.nodes[self.current];
+
+			parent.children.push(id);
+			parent.redo_child = Some(id);
+			self.current = id;
+
+		}
+
+	}
+
+	/// id of the current state
+	pub fn current(&self) -> usize {
+		return self.current;
+	}
+
+	/// number of states, including the initial one
+	pub fn len(&self) -> usize {
+		return self.nodes.len();
+	}
+
+	/// if nothing was edited yet, there's only the initial state
+	pub fn is_empty(&self) -> bool {
+		return self.nodes.len() == 1 && self.pending.is_none();
+	}
+
+	/// remember the current state as the one on disk
+	pub fn mark_saved(&mut self) {
+		self.saved = self.current;
+	}
+
+	/// if the current state is the one on disk
+	pub fn is_saved(&self) -> bool {
+		return self.current == self.saved && self.pending.is_none();
+	}
+
+	/// go to the parent state
+	pub fn undo(&mut self) -> Option<Change> {
+
+		if self.current == 0 {
+			return None;
+		}
+
+		let id = self.current;
+		let node = &self.nodes[id];
+		let parent = node.parent;
+		let change = Change {
+			edits: node.transaction.edits.iter().rev().map(Edit::invert).collect(),
+			cursor: node.transaction.cursor_before,
+		};
+
+		self.nodes[parent].redo_child = Some(id);
+		self.current = parent;
+
+		return Some(change);
+
+	}
+
+	/// go to the most recently visited child state
+	pub fn redo(&mut self) -> Option<Change> {
+
+		let id = self.nodes[self.current].redo_child?;
+		let node = &self.nodes[id];
+
+		self.current = id;
+
+		return Some(Change {
+			edits: node.transaction.edits.clone(),
+			cursor: node.transaction.cursor_after,
+		});
+
+	}
+
+	/// go to any state in the tree, undoing up to the common ancestor and redoing down to the target
+	pub fn goto(&mut self, target: usize) -> Option<Change> {
+
+		if target >= self.nodes.len() || target == self.current {
+			return None;
+		}
+
+		let mut target_path = vec![];
+		let mut id = target;
+
+		loop {
+			target_path.push(id);
+			if id == 0 {
+				break;
+			}
+			id = self.nodes[id].parent;
+		}
+
+		let mut edits = vec![];
+		let mut cursor = self.nodes[self.current].transaction.cursor_after;
+
+		while !target_path.contains(&self.current) {
+			if let Some(change) = self.undo() {
+				edits.extend(change.edits);
+				cursor = change.cursor;
+			}
+		}
+
+		let common = target_path.iter().position(|id| *id == self.current).unwrap_or(0);
+
+		for id in target_path[..common].iter().rev() {
+
+			let parent = self.nodes[*id].parent;
+			let transaction = &self.nodes[*id].transaction;
+
+			edits.extend(transaction.edits.iter().cloned());
+			cursor = transaction.cursor_after;
+			self.nodes[parent].redo_child = Some(*id);
+			self.current = *id;
+
+		}
+
+		return Some(Change {
+			edits: edits,
+			cursor: cursor,
+		});
+
+	}
+
+	/// go back n states in time, across branches
+	pub fn earlier(&mut self, n: usize) -> Option<Change> {
+		return self.goto(self.current.saturating_sub(n));
+	}
+
+	/// go forward n states in time, across branches
+	pub fn later(&mut self, n: usize) -> Option<Change> {
+		return self.goto(clamp(self.current + n, 0, self.nodes.len() - 1));
+	}
+
+	/// go to the sibling branch of the current state
+	pub fn switch_branch(&mut self, forward: bool) -> Option<Change> {
+
+		if self.current == 0 {
+			return None;
+		}
+
+		let siblings = &self.nodes[self.nodes[self.current].parent].children;
+
+		if siblings.len() < 2 {
+			return None;
+		}
+
+		let i = siblings.iter().position(|id| *id == self.current)?;
+		let len = siblings.len();
+		let next = if forward {
+			siblings[(i + 1) % len]
+		} else {
+			siblings[(i + len - 1) % len]
+		};
+
+		return self.goto(next);
+
+	}
+
+	/// write the history next to the user data dir, hash is the hash of the content on disk
+	pub fn save(&self, path: &Path, hash: u64) -> io::Result<()> {
+
+		let file = undo_file_path(path)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user data dir"))?;
+
+		if let Some(dir) = file.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		let data = UndoFile {
+			path: path.to_path_buf(),
+			hash: hash,
+			saved: self.saved,
+			nodes: self.nodes.clone(),
+		};
+
+		let json = serde_json::to_string(&data)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+		return fs::write(file, json);
+
+	}
+
+	/// load the history of a file, only if it was saved with the same content on disk
+	pub fn load(path: &Path, hash: u64) -> Option<Self> {
+
+		let json = fs::read_to_string(undo_file_path(path)?).ok()?;
+		let data: UndoFile = serde_json::from_str(&json).ok()?;
+
+		if data.hash != hash || data.saved >= data.nodes.len() || !valid_nodes(&data.nodes) {
+			return None;
+		}
+
+		return Some(Self {
+			nodes: data.nodes,
+			current: data.saved,
+			saved: data.saved,
+			pending: None,
+		});
+
+	}
+
+}
+
@@ -0,0 +1,72 @@
+// wengwengweng
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::*;
+
+/// get the number of columns (grapheme clusters) in a line
+pub fn col_count(line: &str) -> usize {
+	return line.graphemes(true).count();
+}
+
+/// get the byte offset of a column, clamped to the end of the line
+pub fn col_to_byte(line: &str, col: Col) -> usize {
+
+	if col <= 1 {
+		return 0;
+	}
+
+	return line
+		.grapheme_indices(true)
+		.nth(col as usize - 1)
+		.map(|(i, _)| i)
+		.unwrap_or(line.len());
+
+}
+
+/// get the column that contains a byte offset
+pub fn byte_to_col(line: &str, byte: usize) -> Col {
+
+	let mut col = 1;
+
+	for (i, g) in line.grapheme_indices(true) {
+		if byte < i + g.len() {
+			return col;
+		}
+		col += 1;
+	}
+
+	return col;
+
+}
+
+/// get the grapheme cluster at a column
+pub fn grapheme_at(line: &str, col: Col) -> Option<&str> {
+
+	if col < 1 {
+		return None;
+	}
+
+	return line.graphemes(true).nth(col as usize - 1);
+
+}
+
+/// get the display width of a grapheme cluster, CJK and emoji take 2 cells
+pub fn grapheme_width(g: &str) -> u32 {
+	return clamp(g.width() as u32, 1, 2);
+}
+
+/// get the display width of a string without tabs
+pub fn str_width(s: &str) -> u32 {
+	return s
+		.graphemes(true)
+		.map(grapheme_width)
+		.sum();
+}
+
+/// check if a char can be inserted as text, tab is the only control char that can
+pub fn is_printable(ch: char) -> bool {
+	return ch == '\t' || !ch.is_control();
+}
+
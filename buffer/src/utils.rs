@@ -0,0 +1,31 @@
+// wengwengweng
+
+use std::path::Path;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// fnv-1a hash over chunks of text, stable across runs and platforms
+pub fn hash<'a, I: IntoIterator<Item = &'a str>>(chunks: I) -> u64 {
+
+	let mut hash = FNV_OFFSET;
+
+	for chunk in chunks {
+		for b in chunk.bytes() {
+			hash ^= b as u64;
+			hash = hash.wrapping_mul(FNV_PRIME);
+		}
+	}
+
+	return hash;
+
+}
+
+/// hash of a path, made absolute if possible
+pub fn hash_path(path: &Path) -> u64 {
+
+	let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+	return hash(Some(&*path.to_string_lossy()));
+
+}
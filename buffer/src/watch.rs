@@ -0,0 +1,263 @@
+// wengwengweng
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
+use std::time::Duration;
+use std::time::SystemTime;
+
+#[cfg(target_os = "linux")]
+use inotify::Inotify;
+#[cfg(target_os = "linux")]
+use inotify::WatchMask;
+
+use super::*;
+
+/// how often the mtime is checked where there's no inotify
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// the file as another program left it, while we have unsaved edits
+#[derive(Clone, Debug)]
+pub(crate) struct DiskChange {
+	content: String,
+	format: FileFormat,
+}
+
+/// notices when a file might have changed on disk
+pub(crate) struct Watcher {
+	path: PathBuf,
+	#[cfg(target_os = "linux")]
+	inotify: Option<Inotify>,
+	last_poll: Instant,
+}
+
+impl Watcher {
+
+	fn new(path: &Path) -> Self {
+		return Self {
+			path: path.to_path_buf(),
+			#[cfg(target_os = "linux")]
+			inotify: watch_dir(path),
+			last_poll: Instant::now(),
+		};
+	}
+
+	/// if the file may have changed since the last poll
+	fn poll(&mut self) -> bool {
+
+		#[cfg(target_os = "linux")]
+		{
+			if let Some(inotify) = &mut self.inotify {
+
+				let name = self.path.file_name();
+				let mut buf = [0; 4096];
+				let mut changed = false;
+
+				// read until there are no events left
+				while let Ok(events) = inotify.read_events(&mut buf) {
+
+					let mut empty = true;
+
+					for e in events {
+						empty = false;
+						changed = changed || (e.name.is_some() && e.name == name);
+					}
+
+					if empty {
+						break;
+					}
+
+				}
+
+				return changed;
+
+			}
+		}
+
+		if self.last_poll.elapsed() < POLL_INTERVAL {
+			return false;
+		}
+
+		self.last_poll = Instant::now();
+
+		return true;
+
+	}
+
+}
+
+/// watch the dir of a file, saving through a rename replaces the file we'd be watching
+#[cfg(target_os = "linux")]
+fn watch_dir(path: &Path) -> Option<Inotify> {
+
+	let dir = match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new("."),
+	};
+
+	let inotify = Inotify::init().ok()?;
+	let mask = WatchMask::CLOSE_WRITE
+		| WatchMask::CREATE
+		| WatchMask::DELETE
+		| WatchMask::MOVED_FROM
+		| WatchMask::MOVED_TO;
+
+	inotify.watches().add(dir, mask).ok()?;
+
+	return Some(inotify);
+
+}
+
+fn mtime_of(path: &Path) -> Option<SystemTime> {
+	return fs::metadata(path).and_then(|m| m.modified()).ok();
+}
+
+impl Buffer {
+
+	fn disk_path(&self) -> PathBuf {
+		return fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+	}
+
+	/// remember the file as it is on disk, after reading or writing it
+	pub(crate) fn sync_disk(&mut self) {
+
+		let path = self.disk_path();
+
+		self.disk_mtime = mtime_of(&path);
+		self.disk_base = self.content.to_string();
+		self.disk_change = None;
+
+		if self.watcher.as_ref().map(|w| w.path != path).unwrap_or(true) {
+			self.watcher = Some(Watcher::new(&path));
+		}
+
+	}
+
+	/// look for changes made to the file by something else, it's read again if there are no unsaved edits
+	pub fn check_disk(&mut self) -> Option<Event> {
+
+		if !self.watcher.as_mut()?.poll() {
+			return None;
+		}
+
+		let path = self.disk_path();
+		let mtime = mtime_of(&path);
+
+		if mtime == self.disk_mtime {
+			return None;
+		}
+
+		self.disk_mtime = mtime;
+
+		if mtime.is_none() {
+			return Some(Event::Deleted);
+		}
+
+		let (content, format) = fs::read(&path).ok().and_then(|b| decode(&b))?;
+
+		// only touched
+		if content == self.disk_base && format == self.format {
+			return None;
+		}
+
+		if !self.modified {
+			self.load_disk(&content, format);
+			return Some(Event::Reloaded);
+		}
+
+		self.disk_change = Some(DiskChange {
+			content: content,
+			format: format,
+		});
+
+		return Some(Event::Conflict);
+
+	}
+
+	/// if the file changed on disk since it was read or written, while there are unsaved edits
+	pub fn disk_changed(&self) -> bool {
+		return self.disk_change.is_some() || (self.disk_mtime.is_some() && mtime_of(&self.disk_path()) != self.disk_mtime);
+	}
+
+	/// edit the content into some text, only the part in the middle that differs is replaced so the cursor and marks stay
+	fn replace_content(&mut self, text: &str) {
+
+		let old: Vec<char> = self.content.chars().collect();
+		let new: Vec<char> = text.chars().collect();
+
+		let start = old
+			.iter()
+			.zip(&new)
+			.take_while(|(a, b)| a == b)
+			.count();
+
+		let end = old[start..]
+			.iter()
+			.rev()
+			.zip(new[start..].iter().rev())
+			.take_while(|(a, b)| a == b)
+			.count();
+
+		let text: String = new[start..new.len() - end].iter().collect();
+		let cursor = self.cursor;
+
+		self.push_undo();
+		self.edit_chars(start, old.len() - end, &text);
+		self.push_undo();
+		self.move_to(cursor);
+
+	}
+
+	fn load_disk(&mut self, content: &str, format: FileFormat) {
+
+		self.replace_content(content);
+		self.format = format;
+		self.history.mark_saved();
+		self.saved_hash = utils::hash(self.content.chunks());
+		self.modified = false;
+		self.sync_disk();
+
+	}
+
+	/// read the file again, unsaved edits are dropped but can be undone
+	pub fn reload(&mut self) -> Result<(), Error> {
+
+		let path = self.disk_path();
+		let bytes = fs::read(&path)
+			.map_err(|e| Error::from_io(&path, FileOp::Read, e))?;
+		let (content, format) = decode(&bytes)
+			.ok_or_else(|| Error::InvalidEncoding(path.clone(), None))?;
+
+		self.load_disk(&content, format);
+
+		return Ok(());
+
+	}
+
+	/// merge the unsaved edits with the changes on disk, against the version read before, returns the number of conflicts
+	pub fn merge_disk(&mut self) -> Result<usize, Error> {
+
+		let (theirs, format) = match self.disk_change.take() {
+			Some(change) => (change.content, change.format),
+			None => {
+				let path = self.disk_path();
+				let bytes = fs::read(&path)
+					.map_err(|e| Error::from_io(&path, FileOp::Read, e))?;
+				decode(&bytes).ok_or_else(|| Error::InvalidEncoding(path.clone(), None))?
+			},
+		};
+
+		let merged = merge3(&self.disk_base, &self.content.to_string(), &theirs);
+
+		self.replace_content(&merged.text);
+		self.format = format;
+		self.disk_mtime = mtime_of(&self.disk_path());
+		self.disk_base = theirs;
+
+		return Ok(merged.conflicts);
+
+	}
+
+}
+
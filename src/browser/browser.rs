@@ -58,6 +58,7 @@ pub struct View {
 	previewed_images: HashMap<PathBuf, gfx::Texture>,
 	conf: ViewConf,
 	mode: Mode,
+	message: Option<String>,
 
 }
 
@@ -79,6 +80,7 @@ impl View {
 			textures: textures,
 			conf: ViewConf::default(),
 			mode: Mode::Normal,
+			message: None,
 		};
 
 	}
@@ -87,27 +89,31 @@ impl View {
 
 		let browser = &mut self.browser;
 
-		match browser.selection {
+		let result = match browser.selection {
 
 			Selection::Item(i) => {
 
-				if let Some(item) = browser.listings.get(i) {
-					if let ItemType::Folder = item.kind {
-						browser.cd(item.path.clone());
-					} else if let ItemType::Text = item.kind {
-						if let Ok(buf) = Buffer::from_file(item.path.clone()) {
-							crate::start(crate::buffer::View::new(buf));
-						}
-					}
+				match browser.listings.get(i).cloned() {
+					Some(Item { kind: ItemType::Folder, path, .. }) => browser.cd(path).map_err(|e| format!("{}", e)),
+					Some(Item { kind: ItemType::Text, path, .. }) => {
+						Buffer::from_file(path)
+							.map(|buf| {
+								crate::start(crate::buffer::View::new(buf));
+							})
+							.map_err(|e| format!("{}", e))
+					},
+					_ => Ok(()),
 				}
 
 			},
 
 			Selection::Back => {
-				browser.back();
+				browser.back().map_err(|e| format!("{}", e))
 			}
 
-		}
+		};
+
+		self.message = result.err();
 
 	}
 
@@ -128,7 +134,7 @@ impl Act for View {
 	fn update(&mut self) {
 
 		if window::key_pressed(Key::Back) {
-			self.browser.back();
+			self.message = self.browser.back().err().map(|e| format!("{}", e));
 		}
 
 		if window::key_pressed(Key::Return) {
@@ -304,7 +310,11 @@ impl Act for View {
 		g2d::line(vec2!(0, 0), vec2!(w, 0));
 		g2d::color(color!());
 		g2d::translate(vec2!(8, (bar_height - g2d::font_height()) / 2));
-		g2d::text(&format!("{}", browser.path.display()));
+		if let Some(msg) = &self.message {
+			g2d::text(msg);
+		} else {
+			g2d::text(&format!("{}", browser.path.display()));
+		}
 		g2d::pop();
 
 	}
@@ -1,5 +1,7 @@
 // wengwengweng
 
+use std::cmp;
+
 use dirty::*;
 use dirty::math::*;
 use window::Key;
@@ -8,6 +10,7 @@ use window::Mouse;
 use crate::Act;
 use suite::browser::Browser;
 use suite::buffer::*;
+use suite::buffer::Key as InputKey;
 
 use super::theme::*;
 
@@ -54,26 +57,36 @@ pub struct View {
 	start_line: u32,
 	conf: ViewConf,
 	buffer: Buffer,
+	message: Option<String>,
+	confirm: Option<Substitution>,
 }
 
 impl View {
 
 	pub fn new(buf: Buffer) -> Self {
+
+		let message = if buf.swap_found() {
+			Some(String::from("found unsaved changes in a swap file, :recover to load them or :delswap to drop them"))
+		} else if let Some(e) = buf.filetypes.errors().first() {
+			Some(format!("failed to load filetype: {}", e))
+		} else {
+			None
+		};
+
 		return Self {
 			start_line: 1,
 			buffer: buf,
 			conf: ViewConf::default(),
+			message: message,
+			confirm: None,
 		};
+
 	}
 
 	pub fn view_range(&self) -> (u32, u32) {
 
 		let start = self.start_line;
-		let mut end = start + self.get_view_rows();
-
-		if end > self.buffer.content.len() as u32 {
-			end = self.buffer.content.len() as u32;
-		}
+		let end = self.buffer.line_after_rows(start, self.get_view_rows() as usize);
 
 		return (start, end);
 
@@ -89,11 +102,25 @@ impl View {
 
 	}
 
+	/// update the buffer layout to the window width
+	fn update_layout(&mut self) {
+
+		let size: Vec2 = window::size().into();
+		let width = size.x / self.conf.scale - self.conf.margin_left as f32;
+		let cols = width / self.conf.font.width() as f32;
+
+		self.buffer.layout.width = cmp::max(cols as i32, 1) as u32;
+		self.buffer.layout.tab_width = self.conf.shift_width;
+		self.buffer.layout.wrap = self.conf.wrap;
+		self.buffer.layout.prefix_width = str_width(&self.conf.wrap_prefix);
+
+	}
+
 	pub fn scroll_down(&mut self) {
 
-		if self.start_line < self.buffer.content.len() as u32 {
-			if self.buffer.cursor.line - self.start_line >= self.conf.scroll_off {
-				self.start_line += 1;
+		if self.start_line < self.buffer.line_count() as u32 {
+			if self.buffer.visual_row_from(self.start_line, self.buffer.cursor) >= self.conf.scroll_off as i32 {
+				self.start_line = self.buffer.visible_line_down(self.start_line, 1);
 			}
 		}
 
@@ -102,8 +129,8 @@ impl View {
 	pub fn scroll_up(&mut self) {
 
 		if self.start_line > 1 {
-			if self.buffer.cursor.line < self.start_line + self.get_view_rows() - self.conf.scroll_off {
-				self.start_line -= 1;
+			if self.buffer.visual_row_from(self.start_line, self.buffer.cursor) < (self.get_view_rows() - self.conf.scroll_off) as i32 {
+				self.start_line = self.buffer.visible_line_up(self.start_line, 1);
 			}
 		}
 
@@ -116,29 +143,173 @@ impl View {
 	pub fn screen_to_cursor(&self, pos: Vec2) -> Pos {
 
 		let pos = pos / self.conf.scale;
-		let v_line = (pos.y / self.line_height()) as u32;
-		let line = v_line + self.start_line;
-		let v_col = ((pos.x - self.conf.margin_left as f32) / self.conf.font.width() as f32) as u32 + 1;
-		let col = self.buffer.get_unshifted_col(v_col, line, self.conf.shift_width);
+		let row = (pos.y / self.line_height()) as usize;
+		let x = ((pos.x - self.conf.margin_left as f32) / self.conf.font.width() as f32) as u32 + 1;
 
-		return Pos::new(line, col);
+		return self.buffer.visual_to_pos(self.start_line, row, x);
 
 	}
 
 	pub fn cursor_to_screen(&self, pos: Pos) -> Vec2 {
 
-		let v_line = pos.line - self.start_line;
-		let y = v_line as f32 * self.line_height();
-		let v_col = self.buffer.get_shifted_pos(pos, self.conf.shift_width) as i32;
-		let x = (v_col - 1) * self.conf.font.width() as i32 + self.conf.margin_left;
+		let row = self.buffer.visual_row_from(self.start_line, pos);
+		let y = row as f32 * self.line_height();
+		let (_, v_col) = self.buffer.visual_pos(pos);
+		let x = (v_col as i32 - 1) * self.conf.font.width() as i32 + self.conf.margin_left;
 
 		return vec2!(x, y);
 
 	}
 
-	pub fn start_browser(&self) {
-		if let Ok(browser) = Browser::from_file(self.buffer.path.clone()) {
-			crate::start(crate::browser::View::new(browser));
+	/// get the rects covering some cols of a line on screen, one for each row they are on
+	fn col_rects(&self, ln: Line, start: Col, end: Col) -> Vec<(Vec2, f32)> {
+
+		let line = self.buffer.get_line_at(ln).unwrap_or_default();
+		let cells = self.buffer.layout.cells(&line);
+		let rows = self.buffer.line_rows(ln);
+		let tw = self.conf.font.width() as f32;
+		let mut rects = vec![];
+
+		for (i, r) in rows.iter().enumerate() {
+
+			let last = i + 1 == rows.len();
+
+			// the last row also takes the cols past the line end
+			let row_end = if last {
+				end
+			} else {
+				cmp::min(end, r.end - 1)
+			};
+
+			let a = cmp::max(start, r.start);
+
+			if a > row_end {
+				continue;
+			}
+
+			let p1 = self.cursor_to_screen(Pos::new(ln, a));
+			let p2 = self.cursor_to_screen(Pos::new(ln, row_end));
+			let w = cells.get(row_end as usize - 1).map(|(_, w)| *w).unwrap_or(1);
+
+			rects.push((p1, p2.x - p1.x + w as f32 * tw));
+
+		}
+
+		return rects;
+
+	}
+
+	/// handle what a command asks the view to do
+	fn handle_command(&mut self, result: Result<CmdAction, CmdError>) {
+
+		self.message = None;
+
+		match result {
+			Ok(CmdAction::None) => {},
+			Ok(CmdAction::Quit) => self.start_browser(),
+			Ok(CmdAction::Edit(path)) => {
+				match Buffer::from_file(path.clone()) {
+					Ok(buf) => {
+						crate::start(View::new(buf));
+					},
+					Err(e) => self.message = Some(format!("{}", e)),
+				}
+			},
+			Ok(CmdAction::Confirm(sub)) => {
+				if !sub.is_done() {
+					self.confirm = Some(sub);
+				}
+			},
+			Ok(CmdAction::Message(msg)) => self.message = Some(msg),
+			Err(e) => self.message = Some(format!("{}", e)),
+		}
+
+	}
+
+	/// take a key for the substitution being confirmed
+	fn update_confirm(&mut self) {
+
+		let done = if let Some(sub) = &mut self.confirm {
+
+			if let Some(ch) = window::char_input() {
+				match ch {
+					'y' => sub.accept(),
+					'n' => sub.skip(),
+					'a' => sub.accept_rest(),
+					'q' => sub.quit(),
+					_ => {},
+				}
+			}
+
+			if window::key_pressed(Key::Escape) {
+				sub.quit();
+			}
+
+			if let Some(e) = sub.current() {
+				self.buffer.move_to(e.at);
+			}
+
+			sub.is_done()
+
+		} else {
+			false
+		};
+
+		if done {
+			if let Some(sub) = self.confirm.take() {
+				let count = self.buffer.apply_substitution(&sub);
+				self.message = Some(format!("{} substitutions", count));
+			}
+		}
+
+	}
+
+	/// get the keys pressed this frame for the buffer
+	fn input_keys(&self) -> Vec<InputKey> {
+
+		let mut keys = vec![];
+		let alt = window::key_down(Key::LAlt);
+
+		if let Some(ch) = window::char_input() {
+			if !ch.is_control() && !alt {
+				keys.push(InputKey::Char(ch));
+			}
+		}
+
+		let mut press = |key: Key, input: InputKey| {
+			if window::key_pressed_repeat(key) {
+				keys.push(input);
+			}
+		};
+
+		press(Key::Back, if alt { InputKey::Ctrl('w') } else { InputKey::Backspace });
+		press(Key::Return, InputKey::Enter);
+		press(Key::Escape, InputKey::Escape);
+		press(Key::Up, InputKey::Up);
+		press(Key::Down, InputKey::Down);
+		press(Key::Left, InputKey::Left);
+		press(Key::Right, InputKey::Right);
+
+		if let Mode::Insert = self.buffer.mode {
+			press(Key::Tab, InputKey::Tab);
+		}
+
+		// jump list
+		if alt {
+			press(Key::O, InputKey::Ctrl('o'));
+			press(Key::I, InputKey::Ctrl('i'));
+		}
+
+		return keys;
+
+	}
+
+	pub fn start_browser(&mut self) {
+		match Browser::from_file(self.buffer.path.clone()) {
+			Ok(browser) => {
+				crate::start(crate::browser::View::new(browser));
+			},
+			Err(e) => self.message = Some(format!("{}", e)),
 		}
 	}
 
@@ -148,52 +319,21 @@ impl Act for View {
 
 	fn update(&mut self) {
 
-		match self.buffer.mode {
-
-			Mode::Normal => {
-
-				if let Some(ch) = window::char_input() {
-
-					match ch {
-						'y' => self.buffer.copy_line(),
-						'h' => self.buffer.move_left(),
-						'l' => self.buffer.move_right(),
-						'j' => self.buffer.move_down(),
-						'k' => self.buffer.move_up(),
-						'u' => self.buffer.undo(),
-						'o' => self.buffer.redo(),
-						'd' => self.buffer.del_line(),
-						'<' => self.buffer.move_line_start_insert(),
-						'>' => self.buffer.move_line_end_insert(),
-						':' => self.buffer.start_command(),
-						'?' => self.buffer.start_search(),
-						'H' => self.buffer.move_prev_word(),
-						'L' => self.buffer.move_next_word(),
-						'/' => self.buffer.toggle_comment(),
-						'q' => self.buffer.indent_backward(),
-						'e' => self.buffer.indent_forward(),
-						_ => {},
+		self.update_layout();
 
-					}
+		if self.confirm.is_some() {
+			self.update_confirm();
+			return;
+		}
 
-				}
+		match self.buffer.mode {
 
-				if window::key_pressed(Key::Return) {
-					self.buffer.start_insert();
-				}
+			Mode::Normal => {
 
 				if window::key_pressed(Key::Tab) {
 					self.start_browser();
 				}
 
-				if window::key_pressed(Key::W) {
-					self.buffer.write();
-				}
-
-				if window::key_pressed(Key::Escape) {
-					self.buffer.reset();
-				}
-
 				if window::mouse_pressed(Mouse::Left) {
 
 					let mpos = window::mouse_pos();
@@ -240,49 +380,6 @@ impl Act for View {
 
 			Mode::Insert => {
 
-				if window::key_pressed_repeat(Key::Back) {
-					if window::key_down(Key::LAlt) {
-						self.buffer.del_word();
-					} else {
-						self.buffer.del();
-					}
-				}
-
-				if window::key_pressed_repeat(Key::Return) {
-
-					if window::key_down(Key::LAlt) {
-					} else {
-						self.buffer.break_line();
-					}
-
-				}
-
-				if window::key_pressed_repeat(Key::Up) {
-					self.scroll_up();
-				}
-
-				if window::key_pressed_repeat(Key::Down) {
-					self.scroll_down();
-				}
-
-				if window::key_pressed_repeat(Key::Left) {
-					self.buffer.move_left();
-				}
-
-				if window::key_pressed_repeat(Key::Right) {
-					self.buffer.move_right();
-				}
-
-				if let Some(ch) = window::char_input() {
-					if !window::key_down(Key::LAlt) {
-						self.buffer.insert(ch);
-					}
-				}
-
-				if window::key_pressed(Key::Escape) {
-					self.buffer.start_normal();
-				}
-
 				if let Some(scroll) = window::scroll_delta() {
 
 					if scroll.y > 0 {
@@ -295,46 +392,53 @@ impl Act for View {
 
 			},
 
-			Mode::Command => {
+			_ => {},
 
-				if window::key_pressed(Key::Escape) {
-					self.buffer.start_normal();
-				}
+		}
 
-			},
+		for key in self.input_keys() {
 
-			Mode::Select(_) => {
-				// ...
-			},
+			if key == InputKey::Escape {
+				self.message = None;
+			}
 
-			Mode::Search { .. } => {
+			if let Feed::Command(result) = self.buffer.feed(key) {
+				self.handle_command(result);
+			}
 
-				if window::key_pressed(Key::Escape) {
-					self.buffer.start_normal();
-				}
+		}
 
-			},
+		// scroll by cursor, counting only visible lines, then rows of wrapped lines
+		let cur = self.buffer.cursor.line;
+		let top = self.buffer.visible_line_up(cur, self.conf.scroll_off as usize);
+		let below = self.get_view_rows() as i32 - self.conf.scroll_off as i32 - 1;
 
-		}
+		self.start_line = self.buffer.shown_line_at(self.start_line);
 
-		// scroll by cursor
-		let top = self.buffer.cursor.line as i32 - self.conf.scroll_off as i32;
-		let bottom = self.buffer.cursor.line as i32 - self.get_view_rows() as i32 + self.conf.scroll_off as i32 + 1;
+		if self.start_line > top {
+			self.start_line = top;
+		}
 
-		if self.start_line as i32 > top {
-			if top > 0 {
-				self.start_line = top as u32;
-			} else {
-				self.start_line = 1;
-			}
+		if below >= 0 && self.buffer.visible_rows_between(self.start_line, cur) > below {
+			self.start_line = self.buffer.visible_line_up(cur, below as usize);
 		}
 
-		if (self.start_line as i32) < bottom && bottom < self.buffer.content.len() as i32 {
-			self.start_line = bottom as u32;
+		while below >= 0
+			&& self.start_line < self.buffer.shown_line_at(cur)
+			&& self.buffer.visual_row_from(self.start_line, self.buffer.cursor) > below {
+			self.start_line = self.buffer.visible_line_down(self.start_line, 1);
 		}
 
 		let (start, end) = self.view_range();
 
+		self.buffer.update_swap();
+
+		match self.buffer.check_disk() {
+			Some(Event::Reloaded) => self.message = Some(String::from("file changed on disk, reloaded")),
+			Some(Event::Conflict) => self.message = Some(String::from("file changed on disk, :merge to merge your edits, :reload to drop them, :w! to overwrite it")),
+			Some(Event::Deleted) => self.message = Some(String::from("file removed from disk")),
+			_ => {},
+		}
 		self.buffer.render(start as usize, end as usize);
 		self.buffer.adjust_cursor();
 
@@ -375,6 +479,8 @@ impl Act for View {
 
 			match buf.mode {
 				Mode::Normal => g2d::rect(vec2!(tw, th)),
+				Mode::Select { .. } => g2d::rect(vec2!(tw, th)),
+				Mode::Search { .. } => g2d::rect(vec2!(tw, th)),
 				Mode::Insert => g2d::rect(vec2!(tw / 4, th)),
 				_ => {},
 			}
@@ -383,6 +489,76 @@ impl Act for View {
 
 		};
 
+		// selection
+		let (start, end) = self.view_range();
+
+		for r in buf.selections() {
+
+			for ln in cmp::max(r.start.line, start)..=cmp::min(r.end.line, end) {
+
+				if buf.is_hidden(ln) {
+					continue;
+				}
+
+				let start_col = if ln == r.start.line { r.start.col } else { 1 };
+				let end_col = if ln == r.end.line { r.end.col } else { buf.line_len(ln) };
+
+				for (p, width) in self.col_rects(ln, start_col, cmp::max(start_col, end_col)) {
+					g2d::push();
+					g2d::color(self.conf.theme.selection);
+					g2d::translate(p);
+					g2d::rect(vec2!(width, th));
+					g2d::pop();
+				}
+
+			}
+
+		}
+
+		// search matches
+		let query = buf.active_query();
+
+		if let Some(query) = &query {
+
+			for r in buf.search_matches(query, start, end) {
+
+				if buf.is_hidden(r.start.line) {
+					continue;
+				}
+
+				for (p, width) in self.col_rects(r.start.line, r.start.col, r.end.col) {
+					g2d::push();
+					g2d::color(self.conf.theme.search.background);
+					g2d::translate(p);
+					g2d::rect(vec2!(width, th));
+					g2d::pop();
+				}
+
+			}
+
+		}
+
+		// matching brackets
+		if let Some((open, close)) = buf.matching_pair() {
+
+			for p in &[open, close] {
+
+				if p.line < start || p.line > end || buf.is_hidden(p.line) {
+					continue;
+				}
+
+				for (p, width) in self.col_rects(p.line, p.col, p.col) {
+					g2d::push();
+					g2d::color(self.conf.theme.highlight.background);
+					g2d::translate(p);
+					g2d::rect(vec2!(width, th));
+					g2d::pop();
+				}
+
+			}
+
+		}
+
 		draw_cursor(buf.cursor);
 
 		for c in &buf.child_cursors {
@@ -393,8 +569,13 @@ impl Act for View {
 		g2d::push();
 		g2d::translate(vec2!(self.conf.margin_left, 0));
 
-		for line in &buf.rendered {
+		let lines = buf.visible_lines(start, buf.rendered.len());
+
+		for (line, ln) in buf.rendered.iter().zip(lines) {
 
+			let rows = buf.line_rows(ln);
+			let mut row = 0;
+			let mut col = 1;
 			let mut shift_col = 0;
 
 			g2d::push();
@@ -402,35 +583,76 @@ impl Act for View {
 			// content
 			for chunk in line {
 
-				let splitted = chunk.text.split('\t');
-				let count = splitted.clone().count();
+				let mut rest = chunk.text.as_str();
 
-				for (i, text) in splitted.enumerate() {
+				while !rest.is_empty() {
+
+					// wrap into the next row
+					if row + 1 < rows.len() && col >= rows[row + 1].start {
+
+						row += 1;
+
+						g2d::pop();
+						g2d::translate(vec2!(0, th));
+						g2d::push();
+
+						if let Some(style) = self.conf.theme.spans.get(&Span::Comment) {
+							g2d::color(style.color);
+						} else {
+							g2d::color(self.conf.theme.normal.color);
+						}
+
+						g2d::text(&self.conf.wrap_prefix);
+						g2d::translate(vec2!(buf.layout.prefix_width * tw, 0));
 
-					// text
-					if let Some(style) = self.conf.theme.spans.get(&chunk.span) {
-						g2d::color(style.color);
-					} else {
-						g2d::color(self.conf.theme.normal.color);
 					}
 
-					g2d::text(text);
-					g2d::translate(vec2!(text.len() * tw as usize, 0));
-					shift_col += text.len();
+					// the part of the chunk in this row
+					let cols = col_count(rest) as Col;
+					let take = if row + 1 < rows.len() {
+						cmp::min(rows[row + 1].start - col, cols)
+					} else {
+						cols
+					};
+
+					let (piece, next) = rest.split_at(col_to_byte(rest, take + 1));
 
-					// tab shift
-					if i < count - 1 {
+					rest = next;
+					col += take;
 
-						if self.conf.show_indent {
-							g2d::color(color!(0.24, 0.27, 0.33, 1));
-							g2d::text("|");
+					let splitted = piece.split('\t');
+					let count = splitted.clone().count();
+
+					for (i, text) in splitted.enumerate() {
+
+						// text
+						if let Some(style) = self.conf.theme.spans.get(&chunk.span) {
+							g2d::color(style.color);
+						} else {
+							g2d::color(self.conf.theme.normal.color);
 						}
 
-						let sw = self.conf.shift_width;
-						let offset = sw - shift_col as u32 % sw;
+						let width = str_width(text) as usize;
+
+						g2d::text(text);
+						g2d::translate(vec2!(width * tw as usize, 0));
+						shift_col += width;
 
-						g2d::translate(vec2!(tw * offset as u32, 0));
-						shift_col += offset as usize;
+						// tab shift
+						if i < count - 1 {
+
+							if self.conf.show_indent {
+								g2d::color(color!(0.24, 0.27, 0.33, 1));
+								g2d::text("|");
+							}
+
+							let sw = self.conf.shift_width;
+							let offset = sw - shift_col as u32 % sw;
+
+							g2d::translate(vec2!(tw * offset as u32, 0));
+							shift_col += offset as usize;
+
+						}
 
 					}
 
@@ -445,6 +667,39 @@ impl Act for View {
 
 		g2d::pop();
 
+		// status
+		let status = if self.confirm.is_some() {
+			Some(String::from("replace? (y/n/a/q)"))
+		} else if let Some(text) = buf.command_text() {
+			Some(format!(":{}", text))
+		} else if let Some(msg) = &self.message {
+			Some(msg.clone())
+		} else if let Some(name) = buf.recording() {
+			Some(format!("recording @{}", name))
+		} else {
+			match (buf.search_text(), &query) {
+				(Some(text), _) => Some(format!("?{}", text)),
+				(None, Some(query)) => Some(format!("?{}", query.pattern)),
+				_ => None,
+			}
+		};
+
+		if let Some(mut status) = status {
+
+			if let (Some(query), None) = (&query, buf.command_text()) {
+				if let Some((i, n)) = buf.match_index_at(query, buf.cursor) {
+					status.push_str(&format!("  match {} of {}", i, n));
+				}
+			}
+
+			g2d::push();
+			g2d::translate(vec2!(self.conf.margin_left, h - th));
+			g2d::color(self.conf.theme.normal.color);
+			g2d::text(&status);
+			g2d::pop();
+
+		}
+
 	}
 
 }
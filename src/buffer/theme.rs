@@ -16,6 +16,7 @@ pub struct Theme {
 	pub background: Color,
 	pub cursor: Color,
 	pub cursor_line: Color,
+	pub selection: Color,
 
 }
 
@@ -81,6 +82,7 @@ impl Default for Theme {
 			background: black,
 			cursor: color!(1.0, 1.0, 1.0, 0.5),
 			cursor_line: color!(1.0, 1.0, 1.0, 0.03),
+			selection: color!(0.40, 0.60, 0.80, 0.3),
 
 		};
 	}
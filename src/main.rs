@@ -101,7 +101,14 @@ fn main() {
 	window::init("HoP", 960, 640);
 
 	ctx_init!(HOP, HoP::new());
-	start(browser::View::new(Browser::new(PathBuf::from("/Users/t/Things/hop"))));
+
+	match Browser::new(PathBuf::from("/Users/t/Things/hop")) {
+		Ok(browser) => start(browser::View::new(browser)),
+		Err(e) => {
+			eprintln!("{}", e);
+			return;
+		},
+	};
 
 	app::run(|| {
 